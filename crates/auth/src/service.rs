@@ -90,11 +90,11 @@ impl AuthService {
         )
             .fetch_optional(&self.db_pool)
             .await
-            .map_err(|e| AppError::Database(format!("Error during login: {}", e.to_string())))?;
+            .map_err(|e| AppError::Database(format!("Error during login: {}", e)))?;
 
         let user = user.ok_or(AppError::BadRequest("Invalid email or password".to_string()))?;
 
-        let password_is_valid = bcrypt::verify(&request.password, &user.password_hash).map_err(|e| AppError::Internal(format!("Failed to verify password: {}", e.to_string())))?;
+        let password_is_valid = bcrypt::verify(&request.password, &user.password_hash).map_err(|e| AppError::Internal(format!("Failed to verify password: {}", e)))?;
 
         if !password_is_valid {
             return Err(AppError::BadRequest("Invalid email or password".to_string()));
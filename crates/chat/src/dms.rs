@@ -0,0 +1,281 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use blazing_models::{AppError, ChannelType, CreateDmRequest, DmChannel, MessageType};
+use blazing_ws::SessionRegistry;
+use crate::{MessagesService, WsMessage};
+
+const MAX_GROUP_DM_RECIPIENTS: usize = 10;
+const MAX_GROUP_DM_NAME_LENGTH: usize = 100;
+
+/// Direct message channels. Messages in them go through [`MessagesService`] like any
+/// other channel; this service only manages the channels and their recipients.
+pub struct DmsService {
+    db_pool: PgPool,
+    messages_service: Arc<MessagesService>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl DmsService {
+    pub fn new(
+        db_pool: PgPool,
+        messages_service: Arc<MessagesService>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, messages_service, sessions }
+    }
+
+    /// Opens a DM with a single recipient, reusing the existing one if there is one, or
+    /// creates a group DM with several recipients.
+    pub async fn create_dm(&self, request: CreateDmRequest, user_id: Uuid) -> Result<DmChannel, AppError> {
+        let mut recipients: Vec<Uuid> = Vec::new();
+        for recipient_id in request.recipient_ids {
+            if recipient_id != user_id && !recipients.contains(&recipient_id) {
+                recipients.push(recipient_id);
+            }
+        }
+
+        if recipients.is_empty() {
+            return Err(AppError::BadRequest("A direct message needs at least one other recipient".to_string()));
+        }
+        if recipients.len() + 1 > MAX_GROUP_DM_RECIPIENTS {
+            return Err(AppError::BadRequest(format!(
+                "Group direct messages are limited to {} recipients", MAX_GROUP_DM_RECIPIENTS
+            )));
+        }
+
+        let existing_users = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM users WHERE id = ANY($1)"#,
+            &recipients
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if existing_users != recipients.len() as i64 {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        let blocked = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM relationships
+                    WHERE user_id = ANY($1) AND target_id = $2 AND type = 'blocked'
+                ) as "exists!"
+            "#, &recipients, user_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if blocked {
+            return Err(AppError::Forbidden("Cannot start a direct message with this user".to_string()));
+        }
+
+        let (channel_type, name) = if recipients.len() == 1 {
+            (ChannelType::Dm, String::new())
+        } else {
+            (ChannelType::GroupDm, Self::validate_name(request.name)?)
+        };
+
+        recipients.insert(0, user_id);
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if channel_type == ChannelType::Dm {
+            // Serializes concurrent attempts to open the same DM so only one channel is created.
+            let mut pair = [recipients[0], recipients[1]];
+            pair.sort();
+            sqlx::query!(
+                "SELECT pg_advisory_xact_lock(hashtextextended($1, 0))",
+                format!("dm:{}:{}", pair[0], pair[1])
+            )
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+            let existing = sqlx::query_scalar!(
+                r#"
+                    SELECT c.id
+                    FROM channels c
+                    INNER JOIN dm_recipients a ON a.channel_id = c.id AND a.user_id = $1
+                    INNER JOIN dm_recipients b ON b.channel_id = c.id AND b.user_id = $2
+                    WHERE c.type = 'dm'
+                "#, recipients[0], recipients[1]
+            )
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+            if let Some(channel_id) = existing {
+                tx.commit()
+                    .await
+                    .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+                return self.get_dm(channel_id).await;
+            }
+        }
+
+        let channel_id = sqlx::query_scalar!(
+            r#"
+                INSERT INTO channels (guild_id, name, type)
+                VALUES (NULL, $1, $2)
+                RETURNING id
+            "#, name, channel_type as ChannelType
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create direct message: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO dm_recipients (channel_id, user_id, joined_at)
+                SELECT $1, recipient.user_id, NOW() + recipient.position * INTERVAL '1 microsecond'
+                FROM UNNEST($2::uuid[]) WITH ORDINALITY as recipient(user_id, position)
+            "#, channel_id, &recipients
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create direct message: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let channel = self.get_dm(channel_id).await?;
+
+        self.sessions.subscribe_users(&recipients, &[channel.id]).await;
+        self.sessions.send_to_users(&recipients, WsMessage::DmChannelCreated { channel: channel.clone() }).await;
+
+        Ok(channel)
+    }
+
+    fn validate_name(name: Option<String>) -> Result<String, AppError> {
+        let name = name.map(|name| name.trim().to_string()).unwrap_or_default();
+        if name.len() > MAX_GROUP_DM_NAME_LENGTH {
+            return Err(AppError::BadRequest(format!(
+                "Group name cannot be longer than {} characters", MAX_GROUP_DM_NAME_LENGTH
+            )));
+        }
+        Ok(name)
+    }
+
+    async fn get_dm(&self, channel_id: Uuid) -> Result<DmChannel, AppError> {
+        sqlx::query_as!(DmChannel,
+            r#"
+                SELECT
+                    c.id,
+                    c.type as "channel_type: ChannelType",
+                    NULLIF(c.name, '') as name,
+                    ARRAY(
+                        SELECT user_id FROM dm_recipients WHERE channel_id = c.id ORDER BY joined_at
+                    ) as "recipients!",
+                    c.created_at
+                FROM channels c
+                WHERE c.id = $1 AND c.guild_id IS NULL
+            "#, channel_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Channel not found".to_string()))
+    }
+
+    /// The caller's DMs, most recently active first.
+    pub async fn get_dms(&self, user_id: Uuid) -> Result<Vec<DmChannel>, AppError> {
+        let channels = sqlx::query_as!(DmChannel,
+            r#"
+                SELECT
+                    c.id,
+                    c.type as "channel_type: ChannelType",
+                    NULLIF(c.name, '') as name,
+                    ARRAY(
+                        SELECT user_id FROM dm_recipients WHERE channel_id = c.id ORDER BY joined_at
+                    ) as "recipients!",
+                    c.created_at
+                FROM channels c
+                INNER JOIN dm_recipients me ON me.channel_id = c.id AND me.user_id = $1
+                ORDER BY COALESCE(
+                    (SELECT MAX(created_at) FROM messages WHERE channel_id = c.id),
+                    c.created_at
+                ) DESC
+            "#, user_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(channels)
+    }
+
+    /// Leaves a group DM. The channel is deleted once its last recipient leaves.
+    pub async fn leave_dm(&self, channel_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let channel = self.get_dm(channel_id).await?;
+
+        if !channel.recipients.contains(&user_id) {
+            return Err(AppError::NotFound("Channel not found".to_string()));
+        }
+        if channel.channel_type != ChannelType::GroupDm {
+            return Err(AppError::BadRequest("Only group direct messages can be left".to_string()));
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        // Locks the channel so concurrent leaves see each other's deletes.
+        sqlx::query!("SELECT id FROM channels WHERE id = $1 FOR UPDATE", channel_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to leave direct message: {}", e)))?
+            .ok_or(AppError::NotFound("Channel not found".to_string()))?;
+
+        sqlx::query!(
+            "DELETE FROM dm_recipients WHERE channel_id = $1 AND user_id = $2 RETURNING user_id",
+            channel_id, user_id
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to leave direct message: {}", e)))?
+            .ok_or(AppError::NotFound("Channel not found".to_string()))?;
+
+        let remaining = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM dm_recipients WHERE channel_id = $1"#,
+            channel_id
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to leave direct message: {}", e)))?;
+
+        if remaining == 0 {
+            sqlx::query!("DELETE FROM channels WHERE id = $1", channel_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to leave direct message: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.sessions.unsubscribe_users(&[user_id], &[channel_id]).await;
+
+        if remaining == 0 {
+            return Ok(());
+        }
+
+        // The leave is committed; a failure here should not be reported as a failed leave.
+        if let Err(e) = self.messages_service
+            .create_system_message(channel_id, user_id, MessageType::UserLeave)
+            .await
+        {
+            tracing::warn!("Failed to announce user {} leaving direct message {}: {}", user_id, channel_id, e);
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
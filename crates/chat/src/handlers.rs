@@ -1,20 +1,435 @@
 use std::sync::Arc;
 use axum::{Extension, Json};
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::response::IntoResponse;
 use blazing_auth::CurrentUser;
-use blazing_models::{AppError, GetMessagesRequest};
-use crate::MessagesService;
+use axum::http::StatusCode;
+use blazing_models::{
+    AppError, BulkDeleteMessagesRequest, CreateDmRequest, CreateScheduledMessageRequest, CreateThreadRequest,
+    FriendRequest, MessageHistoryRequest, SearchMessagesRequest, SendMessageRequest, UpdateMessageRequest, UpdateScheduledMessageRequest,
+    UpdateThreadRequest,
+};
+use uuid::Uuid;
+use crate::{DmsService, MessagesService, RelationshipsService, ScheduledMessagesService, ThreadsService};
 
 pub async fn get_messages_handler(
     Extension(current_user): Extension<CurrentUser>,
     State(messages_service): State<Arc<MessagesService>>,
-    Json(request): Json<GetMessagesRequest>
+    Json(request): Json<MessageHistoryRequest>
 ) -> Result<impl IntoResponse, AppError> {
     let messages = messages_service
         .get_messages(request, current_user)
-        .await
-        .map_err(|e| AppError::Internal(format!("Error fetching messages: {}", e)))?;
+        .await?;
 
     Ok(Json(messages))
+}
+
+pub async fn create_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Json(request): Json<SendMessageRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let message = messages_service
+        .create_message(request, current_user.user_id)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(message)))
+}
+
+pub async fn update_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path(message_id): Path<Uuid>,
+    Json(request): Json<UpdateMessageRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let message = messages_service
+        .update_message(message_id, request.content, current_user.user_id)
+        .await?;
+
+    Ok(Json(message))
+}
+
+pub async fn delete_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path(message_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    messages_service
+        .delete_message(message_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn bulk_delete_messages_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path(channel_id): Path<Uuid>,
+    Json(request): Json<BulkDeleteMessagesRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let deleted = messages_service
+        .bulk_delete_messages(channel_id, request, current_user.user_id)
+        .await?;
+
+    Ok(Json(deleted))
+}
+
+pub async fn get_message_edits_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path(message_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let edits = messages_service
+        .get_message_edits(message_id, current_user.user_id)
+        .await?;
+
+    Ok(Json(edits))
+}
+
+pub async fn add_reaction_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path((message_id, emoji)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    messages_service
+        .add_reaction(message_id, emoji, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_reaction_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path((message_id, emoji)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    messages_service
+        .remove_reaction(message_id, emoji, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_pins_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path(channel_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let pins = messages_service
+        .get_pins(channel_id, current_user.user_id)
+        .await?;
+
+    Ok(Json(pins))
+}
+
+pub async fn pin_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    messages_service
+        .set_pinned(channel_id, message_id, true, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn unpin_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    messages_service
+        .set_pinned(channel_id, message_id, false, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn search_messages_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Json(request): Json<SearchMessagesRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let results = messages_service
+        .search_messages(request, current_user.user_id)
+        .await?;
+
+    Ok(Json(results))
+}
+
+pub async fn ack_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    messages_service
+        .ack_message(channel_id, message_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_read_states_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(messages_service): State<Arc<MessagesService>>,
+) -> Result<impl IntoResponse, AppError> {
+    let read_states = messages_service
+        .get_read_states(current_user.user_id)
+        .await?;
+
+    Ok(Json(read_states))
+}
+
+pub async fn get_dms_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(dms_service): State<Arc<DmsService>>,
+) -> Result<impl IntoResponse, AppError> {
+    let channels = dms_service
+        .get_dms(current_user.user_id)
+        .await?;
+
+    Ok(Json(channels))
+}
+
+pub async fn create_dm_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(dms_service): State<Arc<DmsService>>,
+    Json(request): Json<CreateDmRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let channel = dms_service
+        .create_dm(request, current_user.user_id)
+        .await?;
+
+    Ok(Json(channel))
+}
+
+pub async fn leave_dm_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(dms_service): State<Arc<DmsService>>,
+    Path(channel_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    dms_service
+        .leave_dm(channel_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_relationships_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(relationships_service): State<Arc<RelationshipsService>>,
+) -> Result<impl IntoResponse, AppError> {
+    let relationships = relationships_service
+        .get_relationships(current_user.user_id)
+        .await?;
+
+    Ok(Json(relationships))
+}
+
+pub async fn send_friend_request_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(relationships_service): State<Arc<RelationshipsService>>,
+    Json(request): Json<FriendRequest>
+) -> Result<impl IntoResponse, AppError> {
+    relationships_service
+        .send_friend_request(request, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn accept_friend_request_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(relationships_service): State<Arc<RelationshipsService>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    relationships_service
+        .accept_friend_request(user_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_relationship_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(relationships_service): State<Arc<RelationshipsService>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    relationships_service
+        .remove_relationship(user_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn block_user_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(relationships_service): State<Arc<RelationshipsService>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    relationships_service
+        .block_user(user_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_thread_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(channel_id): Path<Uuid>,
+    Json(request): Json<CreateThreadRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let thread = threads_service
+        .create_thread(channel_id, None, request, current_user.user_id)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(thread)))
+}
+
+pub async fn create_message_thread_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(request): Json<CreateThreadRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let thread = threads_service
+        .create_thread(channel_id, Some(message_id), request, current_user.user_id)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(thread)))
+}
+
+pub async fn get_active_threads_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(channel_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let threads = threads_service
+        .get_threads(channel_id, false, current_user.user_id)
+        .await?;
+
+    Ok(Json(threads))
+}
+
+pub async fn get_archived_threads_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(channel_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let threads = threads_service
+        .get_threads(channel_id, true, current_user.user_id)
+        .await?;
+
+    Ok(Json(threads))
+}
+
+pub async fn get_thread_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(thread_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let thread = threads_service
+        .get_thread(thread_id, current_user.user_id)
+        .await?;
+
+    Ok(Json(thread))
+}
+
+pub async fn update_thread_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(thread_id): Path<Uuid>,
+    Json(request): Json<UpdateThreadRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let thread = threads_service
+        .update_thread(thread_id, request, current_user.user_id)
+        .await?;
+
+    Ok(Json(thread))
+}
+
+pub async fn get_thread_members_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(thread_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let members = threads_service
+        .get_members(thread_id, current_user.user_id)
+        .await?;
+
+    Ok(Json(members))
+}
+
+pub async fn join_thread_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(thread_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    threads_service
+        .join_thread(thread_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn leave_thread_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(threads_service): State<Arc<ThreadsService>>,
+    Path(thread_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    threads_service
+        .leave_thread(thread_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn schedule_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(scheduled_service): State<Arc<ScheduledMessagesService>>,
+    Json(request): Json<CreateScheduledMessageRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let scheduled = scheduled_service
+        .schedule_message(request, current_user.user_id)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(scheduled)))
+}
+
+pub async fn get_scheduled_messages_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(scheduled_service): State<Arc<ScheduledMessagesService>>,
+) -> Result<impl IntoResponse, AppError> {
+    let scheduled = scheduled_service
+        .get_scheduled_messages(current_user.user_id)
+        .await?;
+
+    Ok(Json(scheduled))
+}
+
+pub async fn update_scheduled_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(scheduled_service): State<Arc<ScheduledMessagesService>>,
+    Path(scheduled_id): Path<Uuid>,
+    Json(request): Json<UpdateScheduledMessageRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let scheduled = scheduled_service
+        .update_scheduled_message(scheduled_id, request, current_user.user_id)
+        .await?;
+
+    Ok(Json(scheduled))
+}
+
+pub async fn cancel_scheduled_message_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(scheduled_service): State<Arc<ScheduledMessagesService>>,
+    Path(scheduled_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    scheduled_service
+        .cancel_scheduled_message(scheduled_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
\ No newline at end of file
@@ -1,10 +1,24 @@
 mod service;
+mod permissions;
+mod mentions;
+mod dms;
+mod relationships;
+mod threads;
+mod scheduled;
+mod rate_limit;
 mod routes;
 mod handlers;
 mod ws_handler;
 
 use uuid::Uuid;
 pub use service::*;
+pub use permissions::*;
+pub use mentions::*;
+pub use dms::*;
+pub use relationships::*;
+pub use threads::*;
+pub use scheduled::*;
+pub use rate_limit::*;
 
 pub use routes::*;
 pub use handlers::*;
@@ -0,0 +1,114 @@
+use uuid::Uuid;
+
+const EVERYONE_MENTION: &str = "@everyone";
+
+/// Mentions found in a message's content, deduplicated in order of appearance.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParsedMentions {
+    pub users: Vec<Uuid>,
+    pub roles: Vec<Uuid>,
+    pub everyone: bool,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `@everyone` appears as a token of its own, so that `me@everyone.com` or
+/// `@everyones` do not ping the whole channel.
+fn mentions_everyone(content: &str) -> bool {
+    content.match_indices(EVERYONE_MENTION).any(|(start, _)| {
+        let before = content[..start].chars().next_back();
+        let after = content[start + EVERYONE_MENTION.len()..].chars().next();
+
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+/// Extracts `<@user_id>`, `<@&role_id>` and `@everyone` mentions from `content`.
+/// Malformed mentions are left as plain text.
+pub fn parse_mentions(content: &str) -> ParsedMentions {
+    let mut mentions = ParsedMentions {
+        everyone: mentions_everyone(content),
+        ..Default::default()
+    };
+
+    let mut rest = content;
+    while let Some(start) = rest.find("<@") {
+        rest = &rest[start + 2..];
+
+        let (is_role, body) = match rest.strip_prefix('&') {
+            Some(body) => (true, body),
+            None => (false, rest),
+        };
+
+        let Some(end) = body.find('>') else {
+            break;
+        };
+
+        if let Ok(id) = Uuid::parse_str(&body[..end]) {
+            let ids = if is_role { &mut mentions.roles } else { &mut mentions.users };
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: &str = "6f1c2a8e-3b4d-4e5f-8a9b-0c1d2e3f4a5b";
+    const ROLE: &str = "0a1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d";
+
+    fn uuid(id: &str) -> Uuid {
+        Uuid::parse_str(id).unwrap()
+    }
+
+    #[test]
+    fn parses_user_and_role_mentions() {
+        let mentions = parse_mentions(&format!("hi <@{USER}> and <@&{ROLE}>"));
+
+        assert_eq!(mentions.users, vec![uuid(USER)]);
+        assert_eq!(mentions.roles, vec![uuid(ROLE)]);
+        assert!(!mentions.everyone);
+    }
+
+    #[test]
+    fn deduplicates_mentions() {
+        let mentions = parse_mentions(&format!("<@{USER}> <@{USER}> <@&{ROLE}><@&{ROLE}>"));
+
+        assert_eq!(mentions.users, vec![uuid(USER)]);
+        assert_eq!(mentions.roles, vec![uuid(ROLE)]);
+    }
+
+    #[test]
+    fn ignores_malformed_mentions() {
+        assert_eq!(parse_mentions("<@"), ParsedMentions::default());
+        assert_eq!(parse_mentions("<@&"), ParsedMentions::default());
+        assert_eq!(parse_mentions("<@not-a-uuid> <@&> <@>"), ParsedMentions::default());
+        assert_eq!(parse_mentions(&format!("<@{USER}")), ParsedMentions::default());
+        assert_eq!(parse_mentions(&format!("<@&{ROLE} trailing")), ParsedMentions::default());
+    }
+
+    #[test]
+    fn recovers_after_a_malformed_mention() {
+        let mentions = parse_mentions(&format!("<@oops <@{USER}>"));
+
+        assert_eq!(mentions.users, vec![uuid(USER)]);
+    }
+
+    #[test]
+    fn everyone_needs_a_token_boundary() {
+        assert!(parse_mentions("@everyone").everyone);
+        assert!(parse_mentions("hey @everyone!").everyone);
+        assert!(parse_mentions("(@everyone)").everyone);
+        assert!(!parse_mentions("me@everyone.com").everyone);
+        assert!(!parse_mentions("@everyones").everyone);
+        assert!(!parse_mentions("@everyone_else").everyone);
+        assert!(parse_mentions("x@everyone @everyone").everyone);
+    }
+}
@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+use sqlx::PgPool;
+use uuid::Uuid;
+use blazing_models::{AppError, ChannelOverwrites, Overwrite, OverwriteType, Permissions};
+
+/// A guild member with the inputs needed to resolve their permissions.
+struct MemberRoles {
+    guild_id: Uuid,
+    user_id: Uuid,
+    is_owner: bool,
+    permissions: Permissions,
+    role_ids: Vec<Uuid>,
+}
+
+/// A channel joined with one of its overwrites, if it has any. Threads carry the
+/// overwrites of their parent channel.
+struct ChannelOverwriteRow {
+    channel_id: Uuid,
+    guild_id: Uuid,
+    target_type: Option<OverwriteType>,
+    target_id: Option<Uuid>,
+    allow: Option<Permissions>,
+    deny: Option<Permissions>,
+    is_default_role: Option<bool>,
+}
+
+/// Resolves what a user is allowed to do in a guild or channel.
+/// Every access check in chat and guilds goes through this service, which in turn
+/// always computes the result with [`Permissions::resolve`].
+pub struct PermissionsService {
+    db_pool: PgPool,
+}
+
+impl PermissionsService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    async fn load_members(&self, user_ids: &[Uuid], guild_id: Option<Uuid>) -> Result<Vec<MemberRoles>, AppError> {
+        sqlx::query_as!(MemberRoles,
+            r#"
+                SELECT
+                    gm.guild_id,
+                    gm.user_id,
+                    g.owner_id = gm.user_id as "is_owner!",
+                    COALESCE(BIT_OR(r.permissions), 0) as "permissions!: Permissions",
+                    COALESCE(
+                        ARRAY_AGG(r.id) FILTER (WHERE r.id IS NOT NULL AND NOT r.is_default),
+                        '{}'
+                    ) as "role_ids!"
+                FROM guild_members gm
+                INNER JOIN guilds g ON g.id = gm.guild_id
+                LEFT JOIN roles r ON r.guild_id = gm.guild_id AND (
+                    r.is_default OR EXISTS(
+                        SELECT 1 FROM member_roles mr WHERE mr.role_id = r.id AND mr.user_id = gm.user_id
+                    )
+                )
+                WHERE gm.user_id = ANY($1) AND ($2::uuid IS NULL OR gm.guild_id = $2)
+                GROUP BY gm.guild_id, gm.user_id, g.owner_id
+            "#, user_ids, guild_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))
+    }
+
+    async fn load_channels(&self, guild_ids: &[Uuid], channel_id: Option<Uuid>) -> Result<Vec<ChannelOverwriteRow>, AppError> {
+        sqlx::query_as!(ChannelOverwriteRow,
+            r#"
+                SELECT
+                    c.id as channel_id,
+                    c.guild_id as "guild_id!",
+                    o.target_type as "target_type?: OverwriteType",
+                    o.target_id as "target_id?",
+                    o.allow as "allow?: Permissions",
+                    o.deny as "deny?: Permissions",
+                    r.is_default as "is_default_role?"
+                FROM channels c
+                LEFT JOIN channel_overwrites o ON o.channel_id = COALESCE(c.parent_id, c.id)
+                LEFT JOIN roles r ON o.target_type = 'role' AND r.id = o.target_id
+                WHERE c.guild_id = ANY($1) AND ($2::uuid IS NULL OR c.id = $2)
+            "#, guild_ids, channel_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))
+    }
+
+    /// Resolves the permissions of every member in every channel of their guild.
+    /// Returns `(user_id, channel_id, permissions)` triples.
+    fn resolve_channels(members: &[MemberRoles], rows: &[ChannelOverwriteRow]) -> Vec<(Uuid, Uuid, Permissions)> {
+        let mut channels: HashMap<Uuid, (Uuid, Vec<&ChannelOverwriteRow>)> = HashMap::new();
+        for row in rows {
+            channels
+                .entry(row.channel_id)
+                .or_insert_with(|| (row.guild_id, Vec::new()))
+                .1
+                .push(row);
+        }
+
+        let mut resolved = Vec::new();
+        for member in members {
+            for (channel_id, (guild_id, overwrites)) in &channels {
+                if *guild_id != member.guild_id {
+                    continue;
+                }
+
+                let overwrites = Self::member_overwrites(member, overwrites);
+                let permissions = Permissions::resolve(member.is_owner, member.permissions, &overwrites);
+                resolved.push((member.user_id, *channel_id, permissions));
+            }
+        }
+
+        resolved
+    }
+
+    fn member_overwrites(member: &MemberRoles, rows: &[&ChannelOverwriteRow]) -> ChannelOverwrites {
+        let mut overwrites = ChannelOverwrites::default();
+
+        for row in rows {
+            let (Some(target_type), Some(target_id)) = (row.target_type, row.target_id) else {
+                continue;
+            };
+            let allow = row.allow.unwrap_or_default();
+            let deny = row.deny.unwrap_or_default();
+
+            match target_type {
+                OverwriteType::Role if row.is_default_role == Some(true) => {
+                    overwrites.everyone = Overwrite { allow, deny };
+                }
+                OverwriteType::Role if member.role_ids.contains(&target_id) => {
+                    overwrites.roles.allow |= allow;
+                    overwrites.roles.deny |= deny;
+                }
+                OverwriteType::Member if target_id == member.user_id => {
+                    overwrites.member = Overwrite { allow, deny };
+                }
+                _ => {}
+            }
+        }
+
+        overwrites
+    }
+
+    /// Effective guild-level permissions of `user_id`, or `None` if they are not a member.
+    pub async fn guild_permissions(&self, user_id: Uuid, guild_id: Uuid) -> Result<Option<Permissions>, AppError> {
+        let members = self.load_members(&[user_id], Some(guild_id)).await?;
+
+        Ok(members
+            .first()
+            .map(|member| Permissions::resolve(member.is_owner, member.permissions, &ChannelOverwrites::default())))
+    }
+
+    /// Effective permissions of `user_id` in a channel, or `None` if the channel does not
+    /// exist or the user is not a member of its guild (or a recipient of the DM).
+    pub async fn channel_permissions(&self, user_id: Uuid, channel_id: Uuid) -> Result<Option<Permissions>, AppError> {
+        let guild_id = sqlx::query_scalar!("SELECT guild_id FROM channels WHERE id = $1", channel_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let Some(guild_id) = guild_id else {
+            return Ok(None);
+        };
+
+        let Some(guild_id) = guild_id else {
+            return self.dm_permissions(user_id, channel_id).await;
+        };
+
+        let members = self.load_members(&[user_id], Some(guild_id)).await?;
+        if members.is_empty() {
+            return Ok(None);
+        }
+
+        let rows = self.load_channels(&[guild_id], Some(channel_id)).await?;
+
+        Ok(Self::resolve_channels(&members, &rows)
+            .into_iter()
+            .next()
+            .map(|(_, _, permissions)| permissions))
+    }
+
+    /// Recipients of a DM get [`Permissions::DM`]. In a 1:1 DM where either side has blocked
+    /// the other, the channel stays readable but nobody can send or react.
+    async fn dm_permissions(&self, user_id: Uuid, channel_id: Uuid) -> Result<Option<Permissions>, AppError> {
+        let row = sqlx::query!(
+            r#"
+                SELECT
+                    EXISTS(
+                        SELECT 1 FROM dm_recipients WHERE channel_id = $1 AND user_id = $2
+                    ) as "is_recipient!",
+                    EXISTS(
+                        SELECT 1
+                        FROM channels c
+                        INNER JOIN dm_recipients other ON other.channel_id = c.id AND other.user_id <> $2
+                        INNER JOIN relationships r ON r.type = 'blocked' AND (
+                            (r.user_id = $2 AND r.target_id = other.user_id)
+                            OR (r.user_id = other.user_id AND r.target_id = $2)
+                        )
+                        WHERE c.id = $1 AND c.type = 'dm'
+                    ) as "blocked!"
+            "#, channel_id, user_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(Self::resolve_dm(row.is_recipient, row.blocked))
+    }
+
+    fn resolve_dm(is_recipient: bool, blocked: bool) -> Option<Permissions> {
+        if !is_recipient {
+            return None;
+        }
+        if blocked {
+            return Some(Permissions::DM.difference(Permissions::SEND_MESSAGES | Permissions::ADD_REACTIONS));
+        }
+
+        Some(Permissions::DM)
+    }
+
+    pub async fn require_guild_permissions(
+        &self,
+        user_id: Uuid,
+        guild_id: Uuid,
+        required: Permissions
+    ) -> Result<Permissions, AppError> {
+        let permissions = self.guild_permissions(user_id, guild_id)
+            .await?
+            .ok_or(AppError::NotFound("Guild not found".to_string()))?;
+
+        if !permissions.contains(required) {
+            return Err(AppError::Forbidden("Missing permissions".to_string()));
+        }
+
+        Ok(permissions)
+    }
+
+    /// Position of the highest role assigned to `user_id`, or `None` if they own the guild
+    /// and therefore outrank every role.
+    async fn highest_role_position(&self, user_id: Uuid, guild_id: Uuid) -> Result<Option<i32>, AppError> {
+        let row = sqlx::query!(
+            r#"
+                SELECT
+                    g.owner_id = $1 as "is_owner!",
+                    COALESCE(MAX(r.position), 0) as "position!"
+                FROM guilds g
+                LEFT JOIN member_roles mr ON mr.guild_id = g.id AND mr.user_id = $1
+                LEFT JOIN roles r ON r.id = mr.role_id
+                WHERE g.id = $2
+                GROUP BY g.owner_id
+            "#, user_id, guild_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Guild not found".to_string()))?;
+
+        Ok((!row.is_owner).then_some(row.position))
+    }
+
+    /// Fails unless `actor_id` owns the guild or holds a role above `position`.
+    pub async fn require_above(&self, actor_id: Uuid, guild_id: Uuid, position: i32) -> Result<(), AppError> {
+        let highest = self.highest_role_position(actor_id, guild_id).await?;
+
+        Self::check_above(highest, position)
+    }
+
+    /// Checks a highest role position, `None` for the owner, against a role's `position`.
+    pub fn check_above(highest: Option<i32>, position: i32) -> Result<(), AppError> {
+        match highest {
+            Some(highest) if highest <= position => {
+                Err(AppError::Forbidden("Role hierarchy does not allow this".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails unless `actor_id` owns the guild or holds a role above every role of `target_id`.
+    pub async fn require_outranks(&self, actor_id: Uuid, target_id: Uuid, guild_id: Uuid) -> Result<(), AppError> {
+        let Some(position) = self.highest_role_position(target_id, guild_id).await? else {
+            return Err(AppError::Forbidden("Role hierarchy does not allow this".to_string()));
+        };
+
+        self.require_above(actor_id, guild_id, position).await
+    }
+
+    /// Returns the member's guild permissions, failing if `user_id` is not a member.
+    pub async fn require_member(&self, user_id: Uuid, guild_id: Uuid) -> Result<Permissions, AppError> {
+        self.require_guild_permissions(user_id, guild_id, Permissions::empty()).await
+    }
+
+    /// Like [`Self::require_guild_permissions`], but channels the user cannot view are
+    /// reported as not found rather than forbidden.
+    pub async fn require_channel_permissions(
+        &self,
+        user_id: Uuid,
+        channel_id: Uuid,
+        required: Permissions
+    ) -> Result<Permissions, AppError> {
+        let permissions = self.channel_permissions(user_id, channel_id).await?;
+
+        Self::check_channel_permissions(permissions, required)
+    }
+
+    fn check_channel_permissions(permissions: Option<Permissions>, required: Permissions) -> Result<Permissions, AppError> {
+        let permissions = permissions
+            .filter(|permissions| permissions.contains(Permissions::VIEW_CHANNEL))
+            .ok_or(AppError::NotFound("Channel not found".to_string()))?;
+
+        if !permissions.contains(required) {
+            return Err(AppError::Forbidden("Missing permissions".to_string()));
+        }
+
+        Ok(permissions)
+    }
+
+    /// Every channel `user_id` can view, across all of their guilds.
+    pub async fn visible_channels(&self, user_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+        self.channels_with(user_id, Permissions::VIEW_CHANNEL).await
+    }
+
+    /// Every channel in which `user_id` has all of `required`, across all of their guilds
+    /// and direct messages.
+    pub async fn channels_with(&self, user_id: Uuid, required: Permissions) -> Result<Vec<Uuid>, AppError> {
+        let members = self.load_members(&[user_id], None).await?;
+        let guild_ids: Vec<Uuid> = members.iter().map(|member| member.guild_id).collect();
+        let rows = self.load_channels(&guild_ids, None).await?;
+
+        let mut channel_ids: Vec<Uuid> = Self::resolve_channels(&members, &rows)
+            .into_iter()
+            .filter(|(_, _, permissions)| permissions.contains(required))
+            .map(|(_, channel_id, _)| channel_id)
+            .collect();
+
+        if Permissions::DM.contains(required) {
+            let dm_channel_ids = sqlx::query_scalar!(
+                "SELECT channel_id FROM dm_recipients WHERE user_id = $1",
+                user_id
+            )
+                .fetch_all(&self.db_pool)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+            channel_ids.extend(dm_channel_ids);
+        }
+
+        Ok(channel_ids)
+    }
+
+    /// Drops from `channel_ids` the threads `user_id` has not joined. Sessions only follow
+    /// threads their user is a member of, or is currently viewing.
+    pub async fn subscribable_channels(&self, user_id: Uuid, channel_ids: &[Uuid]) -> Result<Vec<Uuid>, AppError> {
+        sqlx::query_scalar!(
+            r#"
+                SELECT c.id
+                FROM channels c
+                WHERE c.id = ANY($1) AND (c.parent_id IS NULL OR EXISTS(
+                    SELECT 1 FROM thread_members tm WHERE tm.thread_id = c.id AND tm.user_id = $2
+                ))
+            "#, channel_ids, user_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))
+    }
+
+    /// Like [`Self::subscribable_channels`] for several users at once, in a single query.
+    pub async fn subscribable_channels_by_user(
+        &self,
+        channel_ids_by_user: &HashMap<Uuid, Vec<Uuid>>
+    ) -> Result<HashMap<Uuid, Vec<Uuid>>, AppError> {
+        let (user_ids, channel_ids): (Vec<Uuid>, Vec<Uuid>) = channel_ids_by_user
+            .iter()
+            .flat_map(|(user_id, channel_ids)| channel_ids.iter().map(move |channel_id| (*user_id, *channel_id)))
+            .unzip();
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT v.user_id as "user_id!", c.id
+                FROM UNNEST($1::uuid[], $2::uuid[]) AS v(user_id, channel_id)
+                JOIN channels c ON c.id = v.channel_id
+                WHERE c.parent_id IS NULL OR EXISTS(
+                    SELECT 1 FROM thread_members tm WHERE tm.thread_id = c.id AND tm.user_id = v.user_id
+                )
+            "#, &user_ids, &channel_ids
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let mut subscribable: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for row in rows {
+            subscribable.entry(row.user_id).or_default().push(row.id);
+        }
+
+        Ok(subscribable)
+    }
+
+    /// For each of `user_ids` that is a member of the guild, the guild channels they can view.
+    pub async fn visible_guild_channels(
+        &self,
+        guild_id: Uuid,
+        user_ids: &[Uuid]
+    ) -> Result<HashMap<Uuid, Vec<Uuid>>, AppError> {
+        let members = self.load_members(user_ids, Some(guild_id)).await?;
+        let rows = self.load_channels(&[guild_id], None).await?;
+
+        let mut visible: HashMap<Uuid, Vec<Uuid>> = members
+            .iter()
+            .map(|member| (member.user_id, Vec::new()))
+            .collect();
+
+        for (user_id, channel_id, permissions) in Self::resolve_channels(&members, &rows) {
+            if permissions.contains(Permissions::VIEW_CHANNEL) {
+                visible.entry(user_id).or_default().push(channel_id);
+            }
+        }
+
+        Ok(visible)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(guild_id: Uuid, permissions: Permissions, role_ids: Vec<Uuid>) -> MemberRoles {
+        MemberRoles { guild_id, user_id: Uuid::new_v4(), is_owner: false, permissions, role_ids }
+    }
+
+    fn row(
+        channel_id: Uuid,
+        guild_id: Uuid,
+        target: Option<(OverwriteType, Uuid, bool)>,
+        allow: Permissions,
+        deny: Permissions
+    ) -> ChannelOverwriteRow {
+        ChannelOverwriteRow {
+            channel_id,
+            guild_id,
+            target_type: target.map(|(target_type, _, _)| target_type),
+            target_id: target.map(|(_, target_id, _)| target_id),
+            allow: target.map(|_| allow),
+            deny: target.map(|_| deny),
+            is_default_role: target.map(|(_, _, is_default)| is_default),
+        }
+    }
+
+    #[test]
+    fn blocked_dm_recipients_cannot_send() {
+        let blocked = PermissionsService::resolve_dm(true, true);
+
+        assert!(PermissionsService::check_channel_permissions(blocked, Permissions::READ_MESSAGE_HISTORY).is_ok());
+        assert!(matches!(
+            PermissionsService::check_channel_permissions(blocked, Permissions::SEND_MESSAGES),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            PermissionsService::check_channel_permissions(blocked, Permissions::ADD_REACTIONS),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn dm_permissions_require_a_recipient() {
+        let open = PermissionsService::resolve_dm(true, false);
+
+        assert!(PermissionsService::check_channel_permissions(open, Permissions::SEND_MESSAGES).is_ok());
+        assert!(matches!(
+            PermissionsService::check_channel_permissions(PermissionsService::resolve_dm(false, false), Permissions::empty()),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn overwrites_only_match_the_members_roles_and_id() {
+        let guild_id = Uuid::new_v4();
+        let channel_id = Uuid::new_v4();
+        let (everyone, assigned, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let member = member(guild_id, Permissions::DEFAULT, vec![assigned]);
+
+        let rows = [
+            row(channel_id, guild_id, Some((OverwriteType::Role, everyone, true)), Permissions::empty(), Permissions::SEND_MESSAGES),
+            row(channel_id, guild_id, Some((OverwriteType::Role, assigned, false)), Permissions::MANAGE_MESSAGES, Permissions::empty()),
+            row(channel_id, guild_id, Some((OverwriteType::Role, other, false)), Permissions::BAN_MEMBERS, Permissions::empty()),
+            row(channel_id, guild_id, Some((OverwriteType::Member, Uuid::new_v4(), false)), Permissions::KICK_MEMBERS, Permissions::empty()),
+            row(channel_id, guild_id, Some((OverwriteType::Member, member.user_id, false)), Permissions::empty(), Permissions::ADD_REACTIONS),
+        ];
+        let rows: Vec<&ChannelOverwriteRow> = rows.iter().collect();
+
+        let overwrites = PermissionsService::member_overwrites(&member, &rows);
+
+        assert_eq!(overwrites.everyone.deny, Permissions::SEND_MESSAGES);
+        assert_eq!(overwrites.roles.allow, Permissions::MANAGE_MESSAGES);
+        assert_eq!(overwrites.member.deny, Permissions::ADD_REACTIONS);
+        assert!(overwrites.member.allow.is_empty());
+    }
+
+    #[test]
+    fn channels_resolve_per_member_within_their_guild() {
+        let (guild_id, other_guild_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let (open, hidden, elsewhere) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let moderator_role = Uuid::new_v4();
+        let everyone = Uuid::new_v4();
+
+        let members = [
+            member(guild_id, Permissions::DEFAULT, vec![]),
+            member(guild_id, Permissions::DEFAULT, vec![moderator_role]),
+        ];
+        let rows = [
+            row(open, guild_id, None, Permissions::empty(), Permissions::empty()),
+            row(hidden, guild_id, Some((OverwriteType::Role, everyone, true)), Permissions::empty(), Permissions::VIEW_CHANNEL),
+            row(hidden, guild_id, Some((OverwriteType::Role, moderator_role, false)), Permissions::VIEW_CHANNEL, Permissions::empty()),
+            row(elsewhere, other_guild_id, None, Permissions::empty(), Permissions::empty()),
+        ];
+
+        let resolved = PermissionsService::resolve_channels(&members, &rows);
+        let can_view = |user_id: Uuid, channel_id: Uuid| resolved
+            .iter()
+            .any(|&(u, c, permissions)| u == user_id && c == channel_id && permissions.contains(Permissions::VIEW_CHANNEL));
+
+        assert_eq!(resolved.len(), 4);
+        assert!(can_view(members[0].user_id, open));
+        assert!(!can_view(members[0].user_id, hidden));
+        assert!(can_view(members[1].user_id, hidden));
+        assert!(!can_view(members[1].user_id, elsewhere));
+    }
+}
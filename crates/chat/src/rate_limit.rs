@@ -0,0 +1,210 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use blazing_models::AppError;
+
+/// Messages a user can send across all channels within [`FLOOD_WINDOW`].
+const FLOOD_LIMIT: usize = 10;
+const FLOOD_WINDOW: Duration = Duration::from_secs(10);
+/// Expired entries are dropped once a map grows past this many users, at most once
+/// every [`PRUNE_INTERVAL`].
+const PRUNE_THRESHOLD: usize = 10_000;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct RateLimitState {
+    /// When each user may next post in each slow mode channel, by `(channel_id, user_id)`.
+    slowmode: HashMap<(Uuid, Uuid), Instant>,
+    /// Send times of each user's recent messages, oldest first.
+    recent: HashMap<Uuid, VecDeque<Instant>>,
+    last_pruned: Option<Instant>,
+}
+
+impl RateLimitState {
+    fn prune(&mut self, now: Instant) {
+        if self.last_pruned.is_some_and(|last_pruned| now.duration_since(last_pruned) < PRUNE_INTERVAL) {
+            return;
+        }
+        if self.recent.len() <= PRUNE_THRESHOLD && self.slowmode.len() <= PRUNE_THRESHOLD {
+            return;
+        }
+
+        self.recent.retain(|_, sent| sent.back().is_some_and(|last| now.duration_since(*last) < FLOOD_WINDOW));
+        self.slowmode.retain(|_, next_allowed| *next_allowed > now);
+        self.last_pruned = Some(now);
+    }
+}
+
+/// A message counted against the limits by [`MessageRateLimiter::try_acquire`], to be
+/// [released](MessageRateLimiter::release) if the message is not sent after all.
+#[derive(Debug)]
+pub struct Reservation {
+    channel_id: Uuid,
+    user_id: Uuid,
+    at: Instant,
+    slowmode: Option<Duration>,
+}
+
+/// In-memory send limits: a per-user flood limit that applies everywhere, and the
+/// per-channel slow mode. A message is checked against both and counted in one step
+/// when it is [acquired](Self::try_acquire), and [released](Self::release) if sending it
+/// fails, so refused or failed messages count against neither.
+#[derive(Default)]
+pub struct MessageRateLimiter {
+    state: Mutex<RateLimitState>,
+}
+
+impl MessageRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a message from `user_id` in `channel_id` against both limits, failing with
+    /// [`AppError::RateLimited`] if it would exceed one. `slowmode` is `None` for channels
+    /// without slow mode and for users exempt from it.
+    pub fn try_acquire(&self, channel_id: Uuid, user_id: Uuid, slowmode: Option<Duration>) -> Result<Reservation, AppError> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(recent) = state.recent.get_mut(&user_id) {
+            while recent.front().is_some_and(|sent| now.duration_since(*sent) >= FLOOD_WINDOW) {
+                recent.pop_front();
+            }
+
+            if recent.len() >= FLOOD_LIMIT {
+                let retry_after = FLOOD_WINDOW - now.duration_since(recent[0]);
+                return Err(AppError::RateLimited {
+                    message: "You are sending messages too quickly".to_string(),
+                    retry_after,
+                });
+            }
+        }
+
+        if let Some(slowmode) = slowmode
+            && let Some(next_allowed) = state.slowmode.get(&(channel_id, user_id))
+            && *next_allowed > now
+        {
+            return Err(AppError::RateLimited {
+                message: format!("Slow mode is enabled: one message every {} seconds", slowmode.as_secs()),
+                retry_after: *next_allowed - now,
+            });
+        }
+
+        state.prune(now);
+        state.recent.entry(user_id).or_default().push_back(now);
+        if let Some(slowmode) = slowmode {
+            state.slowmode.insert((channel_id, user_id), now + slowmode);
+        }
+
+        Ok(Reservation { channel_id, user_id, at: now, slowmode })
+    }
+
+    /// Gives back a message counted by [`Self::try_acquire`] that was not sent.
+    pub fn release(&self, reservation: Reservation) {
+        let Reservation { channel_id, user_id, at, slowmode } = reservation;
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(recent) = state.recent.get_mut(&user_id)
+            && let Some(index) = recent.iter().position(|sent| *sent == at)
+        {
+            recent.remove(index);
+        }
+        if let Some(slowmode) = slowmode
+            && state.slowmode.get(&(channel_id, user_id)) == Some(&(at + slowmode))
+        {
+            state.slowmode.remove(&(channel_id, user_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+    use std::thread;
+    use super::*;
+
+    const SLOWMODE: Option<Duration> = Some(Duration::from_secs(60));
+
+    fn send(limiter: &MessageRateLimiter, channel_id: Uuid, user_id: Uuid, slowmode: Option<Duration>) -> Result<(), AppError> {
+        limiter.try_acquire(channel_id, user_id, slowmode).map(drop)
+    }
+
+    #[test]
+    fn flood_limit_applies_across_channels() {
+        let limiter = MessageRateLimiter::new();
+        let user_id = Uuid::new_v4();
+
+        for _ in 0..FLOOD_LIMIT {
+            send(&limiter, Uuid::new_v4(), user_id, None).unwrap();
+        }
+
+        match send(&limiter, Uuid::new_v4(), user_id, None) {
+            Err(AppError::RateLimited { retry_after, .. }) => assert!(retry_after <= FLOOD_WINDOW),
+            other => panic!("expected a rate limit, got {:?}", other),
+        }
+        assert!(send(&limiter, Uuid::new_v4(), Uuid::new_v4(), None).is_ok());
+    }
+
+    #[test]
+    fn slowmode_is_per_channel_and_user() {
+        let limiter = MessageRateLimiter::new();
+        let (channel_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        send(&limiter, channel_id, user_id, SLOWMODE).unwrap();
+
+        assert!(matches!(send(&limiter, channel_id, user_id, SLOWMODE), Err(AppError::RateLimited { .. })));
+        assert!(send(&limiter, Uuid::new_v4(), user_id, SLOWMODE).is_ok());
+        assert!(send(&limiter, channel_id, Uuid::new_v4(), SLOWMODE).is_ok());
+        assert!(send(&limiter, channel_id, user_id, None).is_ok());
+    }
+
+    #[test]
+    fn released_messages_do_not_count() {
+        let limiter = MessageRateLimiter::new();
+        let (channel_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for _ in 0..FLOOD_LIMIT * 2 {
+            let reservation = limiter.try_acquire(channel_id, user_id, SLOWMODE).unwrap();
+            limiter.release(reservation);
+        }
+
+        send(&limiter, channel_id, user_id, SLOWMODE).unwrap();
+        assert!(send(&limiter, channel_id, user_id, SLOWMODE).is_err());
+    }
+
+    #[test]
+    fn refused_messages_do_not_count() {
+        let limiter = MessageRateLimiter::new();
+        let (channel_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        send(&limiter, channel_id, user_id, SLOWMODE).unwrap();
+        for _ in 0..FLOOD_LIMIT {
+            assert!(send(&limiter, channel_id, user_id, SLOWMODE).is_err());
+        }
+
+        for _ in 1..FLOOD_LIMIT {
+            send(&limiter, Uuid::new_v4(), user_id, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn concurrent_sends_let_one_through_slowmode() {
+        let limiter = MessageRateLimiter::new();
+        let (channel_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let barrier = Barrier::new(FLOOD_LIMIT);
+
+        let accepted = thread::scope(|scope| {
+            let senders: Vec<_> = (0..FLOOD_LIMIT)
+                .map(|_| scope.spawn(|| {
+                    barrier.wait();
+                    send(&limiter, channel_id, user_id, SLOWMODE).is_ok()
+                }))
+                .collect();
+
+            senders.into_iter().map(|sender| sender.join().unwrap()).filter(|accepted| *accepted).count()
+        });
+
+        assert_eq!(accepted, 1);
+    }
+}
@@ -0,0 +1,343 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use blazing_models::{AppError, FriendRequest, Presence, Relationship, RelationshipType};
+use blazing_ws::SessionRegistry;
+use crate::WsMessage;
+
+/// A relationship row without presence, as stored for one side.
+struct RelationshipRow {
+    user_id: Uuid,
+    username: String,
+    avatar_url: Option<String>,
+    relationship_type: RelationshipType,
+    created_at: DateTime<Utc>,
+}
+
+/// Friends, friend requests and blocks. Every change is pushed to the live sessions
+/// of both users as `relationship_add`/`relationship_remove`.
+pub struct RelationshipsService {
+    db_pool: PgPool,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl RelationshipsService {
+    pub fn new(
+        db_pool: PgPool,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, sessions }
+    }
+
+    pub async fn get_relationships(&self, user_id: Uuid) -> Result<Vec<Relationship>, AppError> {
+        let rows = sqlx::query_as!(RelationshipRow,
+            r#"
+                SELECT
+                    u.id as user_id,
+                    u.username,
+                    u.avatar_url,
+                    r.type as "relationship_type: RelationshipType",
+                    r.created_at
+                FROM relationships r
+                INNER JOIN users u ON u.id = r.target_id
+                WHERE r.user_id = $1
+                ORDER BY u.username
+            "#, user_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(self.with_presence(rows).await)
+    }
+
+    async fn with_presence(&self, rows: Vec<RelationshipRow>) -> Vec<Relationship> {
+        // Blocked users never see each other's presence.
+        let visible: Vec<Uuid> = rows
+            .iter()
+            .filter(|row| row.relationship_type != RelationshipType::Blocked)
+            .map(|row| row.user_id)
+            .collect();
+        let online = self.sessions.online_users(&visible).await;
+
+        rows
+            .into_iter()
+            .map(|row| Relationship {
+                presence: if online.contains(&row.user_id) { Presence::Online } else { Presence::Offline },
+                user_id: row.user_id,
+                username: row.username,
+                avatar_url: row.avatar_url,
+                relationship_type: row.relationship_type,
+                created_at: row.created_at,
+            })
+            .collect()
+    }
+
+    /// Sends a friend request to `request.username`, or accepts theirs if they already
+    /// sent one.
+    pub async fn send_friend_request(&self, request: FriendRequest, user_id: Uuid) -> Result<(), AppError> {
+        let target_id = sqlx::query_scalar!("SELECT id FROM users WHERE username = $1", request.username.trim())
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+        if target_id == user_id {
+            return Err(AppError::BadRequest("You cannot send a friend request to yourself".to_string()));
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let (mine, theirs) = Self::lock_pair(&mut tx, user_id, target_id).await?;
+
+        if theirs == Some(RelationshipType::Blocked) {
+            return Err(AppError::BadRequest("Cannot send a friend request to this user".to_string()));
+        }
+
+        match mine {
+            Some(RelationshipType::Friend) => {
+                return Err(AppError::BadRequest("You are already friends with this user".to_string()));
+            }
+            Some(RelationshipType::Blocked) => {
+                return Err(AppError::BadRequest("Unblock this user before sending a friend request".to_string()));
+            }
+            Some(RelationshipType::PendingOutgoing) => return Ok(()),
+            Some(RelationshipType::PendingIncoming) => {
+                Self::set_type(&mut tx, user_id, target_id, RelationshipType::Friend).await?;
+                Self::set_type(&mut tx, target_id, user_id, RelationshipType::Friend).await?;
+            }
+            None => {
+                Self::set_type(&mut tx, user_id, target_id, RelationshipType::PendingOutgoing).await?;
+                Self::set_type(&mut tx, target_id, user_id, RelationshipType::PendingIncoming).await?;
+            }
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.notify_added(user_id, target_id).await;
+        self.notify_added(target_id, user_id).await;
+
+        Ok(())
+    }
+
+    pub async fn accept_friend_request(&self, target_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let (mine, _) = Self::lock_pair(&mut tx, user_id, target_id).await?;
+
+        if mine != Some(RelationshipType::PendingIncoming) {
+            return Err(AppError::NotFound("Friend request not found".to_string()));
+        }
+
+        Self::set_type(&mut tx, user_id, target_id, RelationshipType::Friend).await?;
+        Self::set_type(&mut tx, target_id, user_id, RelationshipType::Friend).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.notify_added(user_id, target_id).await;
+        self.notify_added(target_id, user_id).await;
+
+        Ok(())
+    }
+
+    /// Removes a friend, declines or cancels a request, or unblocks a user.
+    pub async fn remove_relationship(&self, target_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let (mine, theirs) = Self::lock_pair(&mut tx, user_id, target_id).await?;
+
+        let Some(mine) = mine else {
+            return Err(AppError::NotFound("Relationship not found".to_string()));
+        };
+
+        Self::delete_row(&mut tx, user_id, target_id).await?;
+
+        // Unblocking leaves the other side alone; anything else is mutual.
+        let remove_theirs = mine != RelationshipType::Blocked
+            && theirs.is_some_and(|theirs| theirs != RelationshipType::Blocked);
+        if remove_theirs {
+            Self::delete_row(&mut tx, target_id, user_id).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.notify_removed(user_id, target_id).await;
+        if remove_theirs {
+            self.notify_removed(target_id, user_id).await;
+        }
+
+        Ok(())
+    }
+
+    /// Blocks `target_id`, ending any friendship or pending request between the two.
+    pub async fn block_user(&self, target_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        if target_id == user_id {
+            return Err(AppError::BadRequest("You cannot block yourself".to_string()));
+        }
+
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE id = $1) as "exists!""#,
+            target_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if !exists {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let (mine, theirs) = Self::lock_pair(&mut tx, user_id, target_id).await?;
+
+        if mine == Some(RelationshipType::Blocked) {
+            return Ok(());
+        }
+
+        Self::delete_row(&mut tx, user_id, target_id).await?;
+        Self::set_type(&mut tx, user_id, target_id, RelationshipType::Blocked).await?;
+
+        let remove_theirs = theirs.is_some_and(|theirs| theirs != RelationshipType::Blocked);
+        if remove_theirs {
+            Self::delete_row(&mut tx, target_id, user_id).await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.notify_added(user_id, target_id).await;
+        if remove_theirs {
+            self.notify_removed(target_id, user_id).await;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes changes between two users and returns how each side currently
+    /// relates to the other.
+    async fn lock_pair(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        target_id: Uuid
+    ) -> Result<(Option<RelationshipType>, Option<RelationshipType>), AppError> {
+        let mut pair = [user_id, target_id];
+        pair.sort();
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtextextended($1, 0))",
+            format!("relationship:{}:{}", pair[0], pair[1])
+        )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT user_id, type as "relationship_type: RelationshipType"
+                FROM relationships
+                WHERE (user_id = $1 AND target_id = $2) OR (user_id = $2 AND target_id = $1)
+            "#, user_id, target_id
+        )
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let side = |owner: Uuid| rows
+            .iter()
+            .find(|row| row.user_id == owner)
+            .map(|row| row.relationship_type);
+
+        Ok((side(user_id), side(target_id)))
+    }
+
+    async fn set_type(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        target_id: Uuid,
+        relationship_type: RelationshipType
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO relationships (user_id, target_id, type)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, target_id)
+                DO UPDATE SET type = EXCLUDED.type, created_at = NOW()
+            "#, user_id, target_id, relationship_type as RelationshipType
+        )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update relationship: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn delete_row(conn: &mut PgConnection, user_id: Uuid, target_id: Uuid) -> Result<(), AppError> {
+        sqlx::query!(
+            "DELETE FROM relationships WHERE user_id = $1 AND target_id = $2",
+            user_id, target_id
+        )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update relationship: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Sends `user_id` their current relationship with `target_id`. The change is already
+    /// committed by then, so failures are only logged.
+    async fn notify_added(&self, user_id: Uuid, target_id: Uuid) {
+        let row = sqlx::query_as!(RelationshipRow,
+            r#"
+                SELECT
+                    u.id as user_id,
+                    u.username,
+                    u.avatar_url,
+                    r.type as "relationship_type: RelationshipType",
+                    r.created_at
+                FROM relationships r
+                INNER JOIN users u ON u.id = r.target_id
+                WHERE r.user_id = $1 AND r.target_id = $2
+            "#, user_id, target_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await;
+
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                tracing::warn!("Failed to load relationship of {} with {}: {}", user_id, target_id, e);
+                return;
+            }
+        };
+
+        let Some(relationship) = self.with_presence(row.into_iter().collect()).await.pop() else {
+            return;
+        };
+
+        self.sessions.send_to_user(user_id, WsMessage::RelationshipAdded { relationship }).await;
+    }
+
+    async fn notify_removed(&self, user_id: Uuid, target_id: Uuid) {
+        self.sessions.send_to_user(user_id, WsMessage::RelationshipRemoved { user_id: target_id }).await;
+    }
+}
\ No newline at end of file
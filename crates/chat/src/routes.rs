@@ -1,27 +1,127 @@
-use axum::{routing::post, Router, middleware};
+use axum::{routing::{delete, get, patch, post, put}, Router, middleware};
 use std::sync::Arc;
-use blazing_ws::{ws_routes, Broadcaster};
+use blazing_ws::{ws_routes, Broadcaster, SessionRegistry};
 use blazing_auth::{AuthService, auth_middleware};
-use crate::{handlers, MessagesService, ChatWsState, ChatMessageHandler, WsMessage};
+use crate::{
+    handlers, DmsService, MessagesService, RelationshipsService, ScheduledMessagesService, ThreadsService, ChatWsState,
+    ChatMessageHandler, WsMessage,
+};
 use uuid::Uuid;
 
 pub fn create_chat_routes(
     messages_service: Arc<MessagesService>,
+    dms_service: Arc<DmsService>,
+    threads_service: Arc<ThreadsService>,
+    scheduled_service: Arc<ScheduledMessagesService>,
     auth_service: Arc<AuthService>,
     broadcaster: Arc<Broadcaster<Uuid, WsMessage>>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
 ) -> Router {
     let rest_routes = Router::new()
+        .route("/messages", post(handlers::create_message_handler))
         .route("/messages/history", post(handlers::get_messages_handler))
+        .route("/messages/search", post(handlers::search_messages_handler))
+        .route(
+            "/messages/{message_id}",
+            patch(handlers::update_message_handler).delete(handlers::delete_message_handler),
+        )
+        .route("/messages/{message_id}/edits", get(handlers::get_message_edits_handler))
+        .route(
+            "/messages/{message_id}/reactions/{emoji}",
+            put(handlers::add_reaction_handler).delete(handlers::remove_reaction_handler),
+        )
+        .route("/channels/{channel_id}/pins", get(handlers::get_pins_handler))
+        .route(
+            "/channels/{channel_id}/pins/{message_id}",
+            put(handlers::pin_message_handler).delete(handlers::unpin_message_handler),
+        )
+        .route("/channels/{channel_id}/messages/{message_id}/ack", post(handlers::ack_message_handler))
+        .route("/read-states", get(handlers::get_read_states_handler))
+        .route("/channels/{channel_id}/messages/bulk-delete", post(handlers::bulk_delete_messages_handler))
         .layer(middleware::from_fn_with_state(
             auth_service.clone(),
             auth_middleware,
         ))
         .with_state(messages_service.clone());
 
-    let ws_handler = ChatMessageHandler::new(messages_service, auth_service.jwt_secret.clone());
-    let ws_state = ChatWsState::new(ws_handler, (*broadcaster).clone());
+    let dm_routes = Router::new()
+        .route("/dms", get(handlers::get_dms_handler).post(handlers::create_dm_handler))
+        .route("/dms/{channel_id}", delete(handlers::leave_dm_handler))
+        .layer(middleware::from_fn_with_state(
+            auth_service.clone(),
+            auth_middleware,
+        ))
+        .with_state(dms_service);
+
+    let thread_routes = Router::new()
+        .route(
+            "/channels/{channel_id}/threads",
+            get(handlers::get_active_threads_handler).post(handlers::create_thread_handler),
+        )
+        .route("/channels/{channel_id}/threads/archived", get(handlers::get_archived_threads_handler))
+        .route(
+            "/channels/{channel_id}/messages/{message_id}/threads",
+            post(handlers::create_message_thread_handler),
+        )
+        .route(
+            "/threads/{thread_id}",
+            get(handlers::get_thread_handler).patch(handlers::update_thread_handler),
+        )
+        .route("/threads/{thread_id}/members", get(handlers::get_thread_members_handler))
+        .route(
+            "/threads/{thread_id}/members/@me",
+            put(handlers::join_thread_handler).delete(handlers::leave_thread_handler),
+        )
+        .layer(middleware::from_fn_with_state(
+            auth_service.clone(),
+            auth_middleware,
+        ))
+        .with_state(threads_service.clone());
+
+    let scheduled_routes = Router::new()
+        .route(
+            "/scheduled-messages",
+            get(handlers::get_scheduled_messages_handler).post(handlers::schedule_message_handler),
+        )
+        .route(
+            "/scheduled-messages/{scheduled_id}",
+            patch(handlers::update_scheduled_message_handler).delete(handlers::cancel_scheduled_message_handler),
+        )
+        .layer(middleware::from_fn_with_state(
+            auth_service.clone(),
+            auth_middleware,
+        ))
+        .with_state(scheduled_service);
+
+    let ws_handler = ChatMessageHandler::new(messages_service, threads_service, auth_service.jwt_secret.clone());
+    let ws_state = ChatWsState::new(ws_handler, (*broadcaster).clone(), (*sessions).clone());
     let websocket_routes = ws_routes::<ChatMessageHandler>()
         .with_state(ws_state);
 
-    rest_routes.merge(websocket_routes)
+    rest_routes
+        .merge(dm_routes)
+        .merge(thread_routes)
+        .merge(scheduled_routes)
+        .merge(websocket_routes)
+}
+
+pub fn create_relationship_routes(
+    relationships_service: Arc<RelationshipsService>,
+    auth_service: Arc<AuthService>,
+) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(handlers::get_relationships_handler).post(handlers::send_friend_request_handler),
+        )
+        .route(
+            "/{user_id}",
+            put(handlers::accept_friend_request_handler).delete(handlers::remove_relationship_handler),
+        )
+        .route("/{user_id}/block", put(handlers::block_user_handler))
+        .layer(middleware::from_fn_with_state(
+            auth_service,
+            auth_middleware,
+        ))
+        .with_state(relationships_service)
 }
\ No newline at end of file
@@ -0,0 +1,355 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use blazing_models::{
+    AppError, CreateScheduledMessageRequest, Permissions, ScheduledMessage, ScheduledMessageStatus,
+    SendMessageRequest, UpdateScheduledMessageRequest,
+};
+use crate::{validate_content, MessagesService};
+
+const MAX_SCHEDULED_PER_USER: i64 = 100;
+const MAX_SCHEDULE_AHEAD_DAYS: i64 = 30;
+const DELIVERY_BATCH_SIZE: i64 = 50;
+const DELIVERY_INTERVAL: Duration = Duration::from_secs(5);
+/// A claim older than this belongs to a delivery that never finished, typically
+/// because the server stopped, and is picked up again.
+const STALE_CLAIM_SECONDS: f64 = 60.0;
+
+/// A scheduled message claimed for delivery.
+struct DueMessage {
+    id: Uuid,
+    channel_id: Uuid,
+    author_id: Uuid,
+    content: String,
+    reply_to: Option<Uuid>,
+}
+
+/// Messages posted later on behalf of their author. Delivery goes through
+/// [`MessagesService::create_message`], so the author's access is checked again at send time.
+pub struct ScheduledMessagesService {
+    db_pool: PgPool,
+    messages_service: Arc<MessagesService>,
+}
+
+impl ScheduledMessagesService {
+    pub fn new(db_pool: PgPool, messages_service: Arc<MessagesService>) -> Self {
+        Self { db_pool, messages_service }
+    }
+
+    fn validate_send_at(send_at: DateTime<Utc>) -> Result<(), AppError> {
+        let now = Utc::now();
+
+        if send_at <= now {
+            return Err(AppError::BadRequest("Scheduled time must be in the future".to_string()));
+        }
+        if send_at > now + chrono::Duration::days(MAX_SCHEDULE_AHEAD_DAYS) {
+            return Err(AppError::BadRequest(format!(
+                "Messages can be scheduled at most {} days ahead", MAX_SCHEDULE_AHEAD_DAYS
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub async fn schedule_message(
+        &self,
+        request: CreateScheduledMessageRequest,
+        user_id: Uuid
+    ) -> Result<ScheduledMessage, AppError> {
+        validate_content(&request.content)?;
+        Self::validate_send_at(request.send_at)?;
+
+        self.messages_service
+            .get_permissions()
+            .require_channel_permissions(user_id, request.channel_id, Permissions::SEND_MESSAGES)
+            .await?;
+
+        let scheduled_count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM scheduled_messages WHERE author_id = $1"#,
+            user_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if scheduled_count >= MAX_SCHEDULED_PER_USER {
+            return Err(AppError::BadRequest(format!(
+                "You can have at most {} scheduled messages", MAX_SCHEDULED_PER_USER
+            )));
+        }
+
+        sqlx::query_as!(ScheduledMessage,
+            r#"
+                INSERT INTO scheduled_messages (channel_id, author_id, content, reply_to, send_at)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING
+                    id,
+                    channel_id,
+                    content,
+                    reply_to,
+                    send_at,
+                    status as "status: ScheduledMessageStatus",
+                    error,
+                    created_at
+            "#, request.channel_id, user_id, request.content, request.reply_to, request.send_at
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to schedule message: {}", e)))
+    }
+
+    /// The caller's scheduled messages, soonest first.
+    pub async fn get_scheduled_messages(&self, user_id: Uuid) -> Result<Vec<ScheduledMessage>, AppError> {
+        let messages = sqlx::query_as!(ScheduledMessage,
+            r#"
+                SELECT
+                    id,
+                    channel_id,
+                    content,
+                    reply_to,
+                    send_at,
+                    status as "status: ScheduledMessageStatus",
+                    error,
+                    created_at
+                FROM scheduled_messages
+                WHERE author_id = $1
+                ORDER BY send_at, id
+            "#, user_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(messages)
+    }
+
+    /// Changes the content or time of a scheduled message. Failed messages go back to
+    /// pending so they are delivered again.
+    pub async fn update_scheduled_message(
+        &self,
+        scheduled_id: Uuid,
+        request: UpdateScheduledMessageRequest,
+        user_id: Uuid
+    ) -> Result<ScheduledMessage, AppError> {
+        if let Some(content) = &request.content {
+            validate_content(content)?;
+        }
+        if let Some(send_at) = request.send_at {
+            Self::validate_send_at(send_at)?;
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let status = sqlx::query_scalar!(
+            r#"
+                SELECT status as "status: ScheduledMessageStatus"
+                FROM scheduled_messages
+                WHERE id = $1 AND author_id = $2
+                FOR UPDATE
+            "#, scheduled_id, user_id
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Scheduled message not found".to_string()))?;
+
+        if status == ScheduledMessageStatus::Sending {
+            return Err(AppError::BadRequest("This message is already being sent".to_string()));
+        }
+
+        let scheduled = sqlx::query_as!(ScheduledMessage,
+            r#"
+                UPDATE scheduled_messages
+                SET
+                    content = COALESCE($2, content),
+                    send_at = COALESCE($3, send_at),
+                    status = $4,
+                    error = NULL
+                WHERE id = $1
+                RETURNING
+                    id,
+                    channel_id,
+                    content,
+                    reply_to,
+                    send_at,
+                    status as "status: ScheduledMessageStatus",
+                    error,
+                    created_at
+            "#, scheduled_id, request.content, request.send_at,
+            ScheduledMessageStatus::Pending as ScheduledMessageStatus
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update scheduled message: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(scheduled)
+    }
+
+    pub async fn cancel_scheduled_message(&self, scheduled_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let status = sqlx::query_scalar!(
+            r#"
+                DELETE FROM scheduled_messages
+                WHERE id = $1 AND author_id = $2 AND status <> $3
+                RETURNING status as "status: ScheduledMessageStatus"
+            "#, scheduled_id, user_id, ScheduledMessageStatus::Sending as ScheduledMessageStatus
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to cancel scheduled message: {}", e)))?;
+
+        if status.is_none() {
+            let exists = sqlx::query_scalar!(
+                r#"
+                    SELECT EXISTS(
+                        SELECT 1 FROM scheduled_messages WHERE id = $1 AND author_id = $2
+                    ) as "exists!"
+                "#, scheduled_id, user_id
+            )
+                .fetch_one(&self.db_pool)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+            return Err(if exists {
+                AppError::BadRequest("This message is already being sent".to_string())
+            } else {
+                AppError::NotFound("Scheduled message not found".to_string())
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Claims the messages that are due, plus any stale claims, and delivers them.
+    /// Returns the number of messages posted.
+    pub async fn deliver_due_messages(&self) -> Result<usize, AppError> {
+        let due = sqlx::query_as!(DueMessage,
+            r#"
+                UPDATE scheduled_messages
+                SET status = $1, claimed_at = NOW()
+                WHERE id IN (
+                    SELECT id
+                    FROM scheduled_messages
+                    WHERE send_at <= NOW() AND (
+                        status = $2
+                        OR (status = $1 AND claimed_at < NOW() - make_interval(secs => $3))
+                    )
+                    ORDER BY send_at
+                    LIMIT $4
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, channel_id, author_id, content, reply_to
+            "#, ScheduledMessageStatus::Sending as ScheduledMessageStatus,
+            ScheduledMessageStatus::Pending as ScheduledMessageStatus,
+            STALE_CLAIM_SECONDS, DELIVERY_BATCH_SIZE
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to claim scheduled messages: {}", e)))?;
+
+        let mut delivered = 0;
+        for message in due {
+            match self.deliver(message).await {
+                Ok(true) => delivered += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to deliver scheduled message: {}", e),
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Posts a claimed message, unless a previous attempt already did. Errors caused by
+    /// the message itself mark it as failed; rate limits requeue it, and anything else
+    /// leaves the claim to be retried once it goes stale.
+    async fn deliver(&self, message: DueMessage) -> Result<bool, AppError> {
+        let already_posted = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM messages WHERE id = $1) as "exists!""#,
+            message.id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if !already_posted {
+            let result = self.messages_service
+                .create_message(SendMessageRequest {
+                    id: Some(message.id),
+                    channel_id: message.channel_id,
+                    content: message.content,
+                    message_type: None,
+                    attachments: None,
+                    reply_to: message.reply_to,
+                }, message.author_id)
+                .await;
+
+            match result {
+                Ok(_) => {}
+                Err(AppError::Database(e)) => return Err(AppError::Database(e)),
+                Err(AppError::RateLimited { .. }) => {
+                    self.release(message.id).await?;
+                    return Ok(false);
+                }
+                Err(e) => {
+                    self.mark_failed(message.id, &e).await?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        sqlx::query!("DELETE FROM scheduled_messages WHERE id = $1", message.id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(!already_posted)
+    }
+
+    /// Puts a claimed message back in the queue for the next delivery run.
+    async fn release(&self, scheduled_id: Uuid) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE scheduled_messages SET status = $2, claimed_at = NULL WHERE id = $1",
+            scheduled_id, ScheduledMessageStatus::Pending as ScheduledMessageStatus
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn mark_failed(&self, scheduled_id: Uuid, error: &AppError) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE scheduled_messages SET status = $2, error = $3, claimed_at = NULL WHERE id = $1",
+            scheduled_id, ScheduledMessageStatus::Failed as ScheduledMessageStatus, error.to_string()
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Runs [`Self::deliver_due_messages`] periodically until the task is dropped.
+    pub async fn run_delivery(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(DELIVERY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match self.deliver_due_messages().await {
+                Ok(0) => {}
+                Ok(delivered) => tracing::info!("Delivered {} scheduled messages", delivered),
+                Err(e) => tracing::warn!("Failed to deliver scheduled messages: {}", e),
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -1,54 +1,307 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use sqlx::PgPool;
-use blazing_models::{AppError, Attachment, GetMessagesRequest, Message, MessageType, SendMessageRequest};
+use std::time::Duration;
+use sqlx::{PgConnection, PgPool};
+use blazing_models::{
+    AppError, Attachment, ChannelReadState, Message, MessageEdit, MessageHistoryRequest, MessagePage, MessagePreview, MessageType,
+    Permissions, ReactionCount, SearchMessagesRequest, SearchResult, SearchResults, SendMessageRequest,
+    BulkDeleteMessagesRequest,
+};
 use sqlx::types::{Json, Uuid};
+use chrono::{DateTime, Utc};
 use blazing_auth::CurrentUser;
-use blazing_ws::Broadcaster;
-use crate::WsMessage;
+use blazing_ws::{Broadcaster, SessionRegistry};
+use crate::{
+    fetch_thread, parse_mentions, record_thread_activity, MessageRateLimiter, ParsedMentions, PermissionsService,
+    ThreadActivity, WsMessage,
+};
+
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+const MAX_HISTORY_LIMIT: i64 = 100;
+const MAX_CONTENT_LENGTH: usize = 2000;
+const MAX_BULK_DELETE: usize = 100;
+const MAX_EMOJI_LENGTH: usize = 64;
+const MAX_PINS: i64 = 50;
+const DEFAULT_SEARCH_LIMIT: i64 = 25;
+const MAX_SEARCH_QUERY_LENGTH: usize = 256;
+
+/// A search hit, before its message is loaded, with the total hit count. A page past
+/// the last hit is a single row without a hit.
+struct SearchRow {
+    id: Option<Uuid>,
+    highlight: Option<String>,
+    total: i64,
+}
+
+/// Position of a message used as a history cursor.
+struct HistoryCursor {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    deleted: bool,
+}
+
+/// Content rules shared by new and edited messages.
+pub fn validate_content(content: &str) -> Result<(), AppError> {
+    if content.trim().is_empty() {
+        return Err(AppError::BadRequest("Message content cannot be empty".to_string()));
+    }
+    if content.len() > MAX_CONTENT_LENGTH {
+        return Err(AppError::BadRequest("Message too long".to_string()));
+    }
+    Ok(())
+}
 
 pub struct MessagesService {
     db_pool: PgPool,
-    broadcaster: Arc<Broadcaster<Uuid, WsMessage>>
+    broadcaster: Arc<Broadcaster<Uuid, WsMessage>>,
+    permissions: Arc<PermissionsService>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+    rate_limiter: MessageRateLimiter,
 }
 
 impl MessagesService {
-    pub fn new(db_pool: PgPool, broadcaster: Arc<Broadcaster<Uuid, WsMessage>>) -> Self {
-        Self { db_pool, broadcaster }
+    pub fn new(
+        db_pool: PgPool,
+        broadcaster: Arc<Broadcaster<Uuid, WsMessage>>,
+        permissions: Arc<PermissionsService>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, broadcaster, permissions, sessions, rate_limiter: MessageRateLimiter::new() }
     }
 
     pub fn get_pool(&self) -> &PgPool {
         &self.db_pool
     }
 
+    pub fn get_permissions(&self) -> &PermissionsService {
+        &self.permissions
+    }
+
+    /// Posts a message as `author_id`, subject to the channel's slow mode and the
+    /// per-user flood limit.
     pub async fn create_message(&self, request: SendMessageRequest, author_id: Uuid) -> Result<Message, AppError> {
-        if !self.user_has_channel_access(author_id, request.channel_id).await? {
-            return Err(AppError::Forbidden("User is not a member of this guild".to_string()));
+        validate_content(&request.content)?;
+
+        let permissions = self.permissions
+            .require_channel_permissions(author_id, request.channel_id, Permissions::SEND_MESSAGES)
+            .await?;
+
+        let mentions = self.resolve_mentions(
+            request.channel_id,
+            &request.content,
+            permissions,
+            &ParsedMentions::default()
+        ).await?;
+
+        let slowmode = self.slowmode(request.channel_id, permissions).await?;
+        let reservation = self.rate_limiter.try_acquire(request.channel_id, author_id, slowmode)?;
+
+        self.insert_message(request, author_id, mentions)
+            .await
+            .inspect_err(|_| self.rate_limiter.release(reservation))
+    }
+
+    /// The slow mode delay that applies to a user with `permissions` in `channel_id`.
+    /// Threads follow the slow mode of their parent channel; moderators are exempt.
+    async fn slowmode(&self, channel_id: Uuid, permissions: Permissions) -> Result<Option<Duration>, AppError> {
+        if permissions.intersects(Permissions::MANAGE_MESSAGES | Permissions::MANAGE_CHANNELS) {
+            return Ok(None);
         }
 
-        let message_type = request.message_type.unwrap_or(MessageType::Default);
+        let seconds = sqlx::query_scalar!(
+            r#"
+                SELECT GREATEST(c.slowmode_seconds, COALESCE(p.slowmode_seconds, 0)) as "seconds!"
+                FROM channels c
+                LEFT JOIN channels p ON p.id = c.parent_id
+                WHERE c.id = $1
+            "#, channel_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok((seconds > 0).then(|| Duration::from_secs(seconds as u64)))
+    }
 
-        let message = sqlx::query_as!(Message,
-        r#"
-            INSERT INTO messages (channel_id, author_id, content, message_type, attachments)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING
-                id,
-                channel_id,
-                author_id,
-                content,
-                message_type as "message_type: MessageType",
-                attachments as "attachments: Json<Vec<Attachment>>",
-                created_at,
-                updated_at
-        "#, request.channel_id, author_id, request.content,
-            message_type as MessageType,
-            request.attachments.filter(|json| !json.is_empty()) as Option<Json<Vec<Attachment>>>
-    )
+    /// Parses the mentions in `content`, checking that mentioned users are members and
+    /// mentioned roles belong to the channel's guild. `@everyone` only counts as a mention
+    /// with MENTION_EVERYONE. Mentions already in `previous` are kept without checking, so
+    /// an edit does not fail because a mentioned user has since left.
+    async fn resolve_mentions(
+        &self,
+        channel_id: Uuid,
+        content: &str,
+        permissions: Permissions,
+        previous: &ParsedMentions
+    ) -> Result<ParsedMentions, AppError> {
+        let mut mentions = parse_mentions(content);
+        mentions.everyone &= previous.everyone || permissions.contains(Permissions::MENTION_EVERYONE);
+
+        let users: Vec<Uuid> = mentions.users
+            .iter()
+            .filter(|id| !previous.users.contains(id))
+            .copied()
+            .collect();
+        let roles: Vec<Uuid> = mentions.roles
+            .iter()
+            .filter(|id| !previous.roles.contains(id))
+            .copied()
+            .collect();
+
+        if users.is_empty() && roles.is_empty() {
+            return Ok(mentions);
+        }
+
+        let counts = sqlx::query!(
+            r#"
+                SELECT
+                    CASE WHEN c.guild_id IS NULL
+                        THEN (SELECT COUNT(*) FROM dm_recipients WHERE channel_id = c.id AND user_id = ANY($2))
+                        ELSE (SELECT COUNT(*) FROM guild_members WHERE guild_id = c.guild_id AND user_id = ANY($2))
+                    END as "members!",
+                    (SELECT COUNT(*) FROM roles WHERE guild_id = c.guild_id AND id = ANY($3)) as "roles!"
+                FROM channels c
+                WHERE c.id = $1
+            "#, channel_id, &users, &roles
+        )
             .fetch_one(&self.db_pool)
             .await
             .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
 
-        tracing::info!("Author: {}, Content: {}", message.author_id, message.content);
+        if counts.members != users.len() as i64 {
+            return Err(AppError::BadRequest("Mentioned user is not a member of this channel".to_string()));
+        }
+        if counts.roles != roles.len() as i64 {
+            return Err(AppError::BadRequest("Mentioned role does not exist in this guild".to_string()));
+        }
+
+        Ok(mentions)
+    }
+
+    /// Replaces the stored mentions of `message_id`.
+    async fn store_mentions(
+        conn: &mut PgConnection,
+        message_id: Uuid,
+        mentions: &ParsedMentions
+    ) -> Result<(), AppError> {
+        sqlx::query!("UPDATE messages SET mention_everyone = $2 WHERE id = $1", message_id, mentions.everyone)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to store mentions: {}", e)))?;
+
+        sqlx::query!("DELETE FROM message_mentions WHERE message_id = $1", message_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to store mentions: {}", e)))?;
+
+        sqlx::query!("DELETE FROM message_role_mentions WHERE message_id = $1", message_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to store mentions: {}", e)))?;
+
+        sqlx::query!(
+            "INSERT INTO message_mentions (message_id, user_id) SELECT $1, UNNEST($2::uuid[])",
+            message_id, &mentions.users
+        )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to store mentions: {}", e)))?;
+
+        sqlx::query!(
+            "INSERT INTO message_role_mentions (message_id, role_id) SELECT $1, UNNEST($2::uuid[])",
+            message_id, &mentions.roles
+        )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to store mentions: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Posts a system message (join, leave...) on behalf of `author_id` without
+    /// checking channel access, since the author may no longer be a member.
+    pub async fn create_system_message(
+        &self,
+        channel_id: Uuid,
+        author_id: Uuid,
+        message_type: MessageType
+    ) -> Result<Message, AppError> {
+        self.insert_message(SendMessageRequest {
+            id: None,
+            channel_id,
+            content: String::new(),
+            message_type: Some(message_type),
+            attachments: None,
+            reply_to: None,
+        }, author_id, ParsedMentions::default()).await
+    }
+
+    async fn insert_message(
+        &self,
+        request: SendMessageRequest,
+        author_id: Uuid,
+        mentions: ParsedMentions
+    ) -> Result<Message, AppError> {
+        let message_type = match (request.message_type, request.reply_to) {
+            (None | Some(MessageType::Default | MessageType::Reply), Some(_)) => MessageType::Reply,
+            (Some(MessageType::Reply), None) => {
+                return Err(AppError::BadRequest("Replies must reference a message".to_string()));
+            }
+            (Some(_), Some(_)) => {
+                return Err(AppError::BadRequest("Only regular messages can be replies".to_string()));
+            }
+            (message_type, None) => message_type.unwrap_or(MessageType::Default),
+        };
+
+        if let Some(reply_to) = request.reply_to {
+            let exists = sqlx::query_scalar!(
+                r#"
+                    SELECT EXISTS(
+                        SELECT 1 FROM messages WHERE id = $1 AND channel_id = $2 AND deleted_at IS NULL
+                    ) as "exists!"
+                "#, reply_to, request.channel_id
+            )
+                .fetch_one(&self.db_pool)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+            if !exists {
+                return Err(AppError::BadRequest("Referenced message not found in this channel".to_string()));
+            }
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let message_id = sqlx::query_scalar!(
+            r#"
+                INSERT INTO messages (id, channel_id, author_id, content, message_type, attachments, reply_to)
+                VALUES (COALESCE($1, gen_random_uuid()), $2, $3, $4, $5, $6, $7)
+                RETURNING id
+            "#, request.id, request.channel_id, author_id, request.content,
+            message_type as MessageType,
+            request.attachments.filter(|json| !json.is_empty()) as Option<Json<Vec<Attachment>>>,
+            request.reply_to
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Self::store_mentions(&mut tx, message_id, &mentions).await?;
+
+        let thread_activity = record_thread_activity(&mut tx, request.channel_id, author_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if let Some(activity) = thread_activity {
+            self.announce_thread_activity(request.channel_id, author_id, activity).await?;
+        }
+
+        let message = self.get_message(message_id, None).await?;
 
         if let Err(e) = self.broadcaster.broadcast(
             &request.channel_id,
@@ -60,55 +313,798 @@ impl MessagesService {
         Ok(message)
     }
 
-    pub async fn get_messages(&self, request: GetMessagesRequest, current_user: CurrentUser) -> Result<Vec<Message>, AppError> {
-        if !self.user_has_channel_access(current_user.user_id, request.channel_id).await? {
-            return Err(AppError::Forbidden("User is not a member of this guild".to_string()));
+    /// Posting in a thread joins it and brings it back from the archive.
+    async fn announce_thread_activity(
+        &self,
+        thread_id: Uuid,
+        author_id: Uuid,
+        activity: ThreadActivity
+    ) -> Result<(), AppError> {
+        if activity.joined {
+            self.sessions.subscribe_users(&[author_id], &[thread_id]).await;
+            if let Err(e) = self.broadcaster.broadcast(
+                &thread_id,
+                WsMessage::ThreadMembersUpdate { thread_id, user_id: author_id, joined: true }
+            ).await {
+                tracing::warn!("Failed to broadcast thread members update: {}", e);
+            }
+        }
+
+        if activity.unarchived {
+            let thread = fetch_thread(&self.db_pool, thread_id).await?;
+            let parent_id = thread.parent_id;
+            if let Err(e) = self.broadcaster.broadcast(
+                &parent_id,
+                WsMessage::ThreadUpdated { thread }
+            ).await {
+                tracing::warn!("Failed to broadcast thread update: {}", e);
+            }
         }
 
-        let messages = sqlx::query_as!(Message,
+        Ok(())
+    }
+
+    /// Replaces the content of one of `user_id`'s own messages, keeping the previous
+    /// content in the edit history.
+    pub async fn update_message(&self, message_id: Uuid, content: String, user_id: Uuid) -> Result<Message, AppError> {
+        validate_content(&content)?;
+
+        let existing = sqlx::query!(
             r#"
                 SELECT
-                    id,
-                    channel_id,
-                    author_id,
-                    content,
-                    message_type as "message_type: MessageType",
-                    attachments as "attachments: Json<Vec<Attachment>>",
-                    created_at,
-                    updated_at
-                FROM messages
-                WHERE channel_id = $1
-                ORDER BY created_at DESC
-            "#, request.channel_id
+                    m.channel_id,
+                    m.author_id,
+                    m.message_type as "message_type: MessageType",
+                    m.mention_everyone,
+                    ARRAY(SELECT user_id FROM message_mentions WHERE message_id = m.id) as "mentions!",
+                    ARRAY(SELECT role_id FROM message_role_mentions WHERE message_id = m.id) as "mention_roles!"
+                FROM messages m
+                WHERE m.id = $1 AND m.deleted_at IS NULL
+            "#, message_id
         )
-            .fetch_all(&self.db_pool)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+        let permissions = self.permissions
+            .require_channel_permissions(user_id, existing.channel_id, Permissions::empty())
+            .await
+            .map_err(|_| AppError::NotFound("Message not found".to_string()))?;
+
+        if existing.author_id != user_id {
+            return Err(AppError::Forbidden("You can only edit your own messages".to_string()));
+        }
+
+        if !matches!(existing.message_type, MessageType::Default | MessageType::Reply) {
+            return Err(AppError::BadRequest("System messages cannot be edited".to_string()));
+        }
+
+        let previous = ParsedMentions {
+            users: existing.mentions,
+            roles: existing.mention_roles,
+            everyone: existing.mention_everyone,
+        };
+        let mentions = self.resolve_mentions(existing.channel_id, &content, permissions, &previous).await?;
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let previous_content = sqlx::query_scalar!(
+            "SELECT content FROM messages WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+            message_id
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+        sqlx::query!(
+            "INSERT INTO message_edits (message_id, content) VALUES ($1, $2)",
+            message_id, previous_content
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update message: {}", e)))?;
+
+        sqlx::query!(
+            "UPDATE messages SET content = $2, updated_at = NOW() WHERE id = $1",
+            message_id, content
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update message: {}", e)))?;
+
+        Self::store_mentions(&mut tx, message_id, &mentions).await?;
+
+        tx.commit()
             .await
             .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
 
-        Ok(messages)
+        let message = self.get_message(message_id, None).await?;
+
+        if let Err(e) = self.broadcaster.broadcast(
+            &message.channel_id,
+            WsMessage::MessageUpdated { message: message.clone() }
+        ).await {
+            tracing::warn!("Failed to broadcast message update: {}", e);
+        }
+
+        Ok(message)
+    }
+
+    /// Deletes a message. Authors can delete their own messages, moderators anyone's.
+    pub async fn delete_message(&self, message_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let existing = sqlx::query!(
+            "SELECT channel_id, author_id FROM messages WHERE id = $1 AND deleted_at IS NULL",
+            message_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+        let required = if existing.author_id == user_id {
+            Permissions::empty()
+        } else {
+            Permissions::MANAGE_MESSAGES
+        };
+
+        self.permissions
+            .require_channel_permissions(user_id, existing.channel_id, required)
+            .await?;
+
+        self.soft_delete(existing.channel_id, &[message_id]).await?;
+
+        Ok(())
     }
 
-    pub async fn user_has_channel_access(
+    /// Deletes several messages of one channel at once. Requires MANAGE_MESSAGES.
+    pub async fn bulk_delete_messages(
         &self,
-        user_id: Uuid,
-        channel_id: Uuid
-    ) -> Result<bool, AppError> {
+        channel_id: Uuid,
+        request: BulkDeleteMessagesRequest,
+        user_id: Uuid
+    ) -> Result<Vec<Uuid>, AppError> {
+        if request.message_ids.is_empty() || request.message_ids.len() > MAX_BULK_DELETE {
+            return Err(AppError::BadRequest(format!(
+                "Between 1 and {} messages can be deleted at once", MAX_BULK_DELETE
+            )));
+        }
+
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::MANAGE_MESSAGES)
+            .await?;
+
+        self.soft_delete(channel_id, &request.message_ids).await
+    }
+
+    /// Marks the given messages of `channel_id` as deleted and broadcasts `MessageDeleted`
+    /// for each one that was not already deleted.
+    async fn soft_delete(&self, channel_id: Uuid, message_ids: &[Uuid]) -> Result<Vec<Uuid>, AppError> {
+        let deleted = sqlx::query_scalar!(
+            r#"
+                UPDATE messages
+                SET deleted_at = NOW()
+                WHERE channel_id = $1 AND id = ANY($2) AND deleted_at IS NULL
+                RETURNING id
+            "#, channel_id, message_ids
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete messages: {}", e)))?;
+
+        for message_id in &deleted {
+            if let Err(e) = self.broadcaster.broadcast(
+                &channel_id,
+                WsMessage::MessageDeleted { channel_id, message_id: *message_id }
+            ).await {
+                tracing::warn!("Failed to broadcast message deletion: {}", e);
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    fn validate_emoji(emoji: &str) -> Result<(), AppError> {
+        if emoji.is_empty() || emoji.len() > MAX_EMOJI_LENGTH || emoji.chars().any(char::is_whitespace) {
+            return Err(AppError::BadRequest("Invalid emoji".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Channel of a message that `user_id` can react to.
+    async fn require_reactable(&self, message_id: Uuid, user_id: Uuid) -> Result<Uuid, AppError> {
+        let channel_id = sqlx::query_scalar!(
+            "SELECT channel_id FROM messages WHERE id = $1 AND deleted_at IS NULL",
+            message_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+        self.permissions
+            .require_channel_permissions(
+                user_id,
+                channel_id,
+                Permissions::READ_MESSAGE_HISTORY | Permissions::ADD_REACTIONS
+            )
+            .await?;
+
+        Ok(channel_id)
+    }
+
+    pub async fn add_reaction(&self, message_id: Uuid, emoji: String, user_id: Uuid) -> Result<(), AppError> {
+        Self::validate_emoji(&emoji)?;
+        let channel_id = self.require_reactable(message_id, user_id).await?;
+
         let result = sqlx::query!(
             r#"
-            SELECT EXISTS(
-                SELECT 1
-                FROM channels c
-                INNER JOIN guild_members gm ON c.guild_id = gm.guild_id
-                WHERE c.id = $1 AND gm.user_id = $2
-            ) as "exists!"
-            "#,
-            channel_id,
-            user_id
+                INSERT INTO message_reactions (message_id, user_id, emoji)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+            "#, message_id, user_id, emoji
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to add reaction: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        if let Err(e) = self.broadcaster.broadcast(
+            &channel_id,
+            WsMessage::ReactionAdded { channel_id, message_id, user_id, emoji }
+        ).await {
+            tracing::warn!("Failed to broadcast reaction: {}", e);
+        }
+
+        Ok(())
+    }
+
+    pub async fn remove_reaction(&self, message_id: Uuid, emoji: String, user_id: Uuid) -> Result<(), AppError> {
+        let channel_id = sqlx::query_scalar!(
+            "SELECT channel_id FROM messages WHERE id = $1 AND deleted_at IS NULL",
+            message_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::empty())
+            .await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM message_reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3",
+            message_id, user_id, emoji
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to remove reaction: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        if let Err(e) = self.broadcaster.broadcast(
+            &channel_id,
+            WsMessage::ReactionRemoved { channel_id, message_id, user_id, emoji }
+        ).await {
+            tracing::warn!("Failed to broadcast reaction removal: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Pins or unpins a message of `channel_id`. Requires MANAGE_MESSAGES; pinning fails once
+    /// the channel has [`MAX_PINS`] pinned messages.
+    pub async fn set_pinned(&self, channel_id: Uuid, message_id: Uuid, pinned: bool, user_id: Uuid) -> Result<(), AppError> {
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::MANAGE_MESSAGES)
+            .await?;
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        // Serializes pin changes per channel so the cap cannot be overshot.
+        sqlx::query!("SELECT id FROM channels WHERE id = $1 FOR UPDATE", channel_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let currently_pinned = sqlx::query_scalar!(
+            r#"
+                SELECT pinned_at IS NOT NULL as "pinned!"
+                FROM messages
+                WHERE id = $1 AND channel_id = $2 AND deleted_at IS NULL
+            "#, message_id, channel_id
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+        if currently_pinned == pinned {
+            return Ok(());
+        }
+
+        if pinned {
+            let pin_count = sqlx::query_scalar!(
+                r#"
+                    SELECT COUNT(*) as "count!"
+                    FROM messages
+                    WHERE channel_id = $1 AND pinned_at IS NOT NULL AND deleted_at IS NULL
+                "#, channel_id
+            )
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+            if pin_count >= MAX_PINS {
+                return Err(AppError::BadRequest(format!("A channel can have at most {} pins", MAX_PINS)));
+            }
+        }
+
+        sqlx::query!(
+            "UPDATE messages SET pinned_at = CASE WHEN $2 THEN NOW() END WHERE id = $1",
+            message_id, pinned
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update pins: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if let Err(e) = self.broadcaster.broadcast(
+            &channel_id,
+            WsMessage::ChannelPinsUpdate { channel_id, message_id, pinned }
+        ).await {
+            tracing::warn!("Failed to broadcast pins update: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Pinned messages of a channel, most recently pinned first.
+    pub async fn get_pins(&self, channel_id: Uuid, user_id: Uuid) -> Result<Vec<Message>, AppError> {
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::READ_MESSAGE_HISTORY)
+            .await?;
+
+        let message_ids = sqlx::query_scalar!(
+            r#"
+                SELECT id
+                FROM messages
+                WHERE channel_id = $1 AND pinned_at IS NOT NULL AND deleted_at IS NULL
+                ORDER BY pinned_at DESC
+            "#, channel_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.load_messages(&message_ids, Some(user_id)).await
+    }
+
+    /// Marks `message_id` as the last message `user_id` has read in `channel_id` and
+    /// notifies the user's other sessions. Acking a message older than the current marker
+    /// is a no-op.
+    pub async fn ack_message(&self, channel_id: Uuid, message_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::READ_MESSAGE_HISTORY)
+            .await?;
+
+        let exists = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM messages WHERE id = $1 AND channel_id = $2 AND deleted_at IS NULL
+                ) as "exists!"
+            "#, message_id, channel_id
         )
             .fetch_one(&self.db_pool)
             .await
             .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
 
-        Ok(result.exists)
+        if !exists {
+            return Err(AppError::NotFound("Message not found".to_string()));
+        }
+
+        let result = sqlx::query!(
+            r#"
+                INSERT INTO read_states (user_id, channel_id, last_read_message_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, channel_id)
+                DO UPDATE SET last_read_message_id = EXCLUDED.last_read_message_id, updated_at = NOW()
+                WHERE NOT EXISTS(
+                    SELECT 1
+                    FROM messages current, messages acked
+                    WHERE current.id = read_states.last_read_message_id
+                        AND acked.id = EXCLUDED.last_read_message_id
+                        AND (current.created_at, current.id) >= (acked.created_at, acked.id)
+                )
+            "#, user_id, channel_id, message_id
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update read state: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        self.sessions.send_to_user(user_id, WsMessage::MessageAcked { channel_id, message_id }).await;
+
+        Ok(())
+    }
+
+    /// Unread and mention counts for every channel `user_id` can read.
+    pub async fn get_read_states(&self, user_id: Uuid) -> Result<Vec<ChannelReadState>, AppError> {
+        let channel_ids = self.permissions
+            .channels_with(user_id, Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY)
+            .await?;
+
+        let read_states = sqlx::query_as!(ChannelReadState,
+            r#"
+                SELECT
+                    c.id as "channel_id!",
+                    rs.last_read_message_id as "last_read_message_id?",
+                    counts.unread as "unread_count!",
+                    counts.mentions as "mention_count!"
+                FROM UNNEST($1::uuid[]) as c(id)
+                LEFT JOIN read_states rs ON rs.channel_id = c.id AND rs.user_id = $2
+                LEFT JOIN messages lr ON lr.id = rs.last_read_message_id
+                CROSS JOIN LATERAL (
+                    SELECT
+                        COUNT(*) as unread,
+                        COUNT(*) FILTER (WHERE
+                            m.mention_everyone
+                            OR EXISTS(SELECT 1 FROM message_mentions mm WHERE mm.message_id = m.id AND mm.user_id = $2)
+                            OR EXISTS(
+                                SELECT 1
+                                FROM message_role_mentions rm
+                                INNER JOIN member_roles mr ON mr.role_id = rm.role_id AND mr.user_id = $2
+                                WHERE rm.message_id = m.id
+                            )
+                        ) as mentions
+                    FROM messages m
+                    WHERE m.channel_id = c.id
+                        AND m.deleted_at IS NULL
+                        AND m.author_id <> $2
+                        AND (lr.id IS NULL OR (m.created_at, m.id) > (lr.created_at, lr.id))
+                ) counts
+            "#, &channel_ids, user_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(read_states)
+    }
+
+    /// Prior revisions of a message, oldest first.
+    pub async fn get_message_edits(&self, message_id: Uuid, user_id: Uuid) -> Result<Vec<MessageEdit>, AppError> {
+        let channel_id = sqlx::query_scalar!("SELECT channel_id FROM messages WHERE id = $1 AND deleted_at IS NULL", message_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::READ_MESSAGE_HISTORY)
+            .await?;
+
+        let edits = sqlx::query_as!(MessageEdit,
+            r#"
+                SELECT id, message_id, content, edited_at
+                FROM message_edits
+                WHERE message_id = $1
+                ORDER BY edited_at ASC
+            "#, message_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(edits)
+    }
+
+    pub async fn get_messages(&self, request: MessageHistoryRequest, current_user: CurrentUser) -> Result<MessagePage, AppError> {
+        let cursors = [request.before, request.after, request.around].iter().flatten().count();
+        if cursors > 1 {
+            return Err(AppError::BadRequest("Only one of before, after and around may be set".to_string()));
+        }
+
+        let limit = request.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+        if !(1..=MAX_HISTORY_LIMIT).contains(&limit) {
+            return Err(AppError::BadRequest(format!("Limit must be between 1 and {}", MAX_HISTORY_LIMIT)));
+        }
+
+        self.permissions
+            .require_channel_permissions(current_user.user_id, request.channel_id, Permissions::READ_MESSAGE_HISTORY)
+            .await?;
+
+        let channel_id = request.channel_id;
+        let viewer_id = current_user.user_id;
+
+        if let Some(after) = request.after {
+            let cursor = self.get_cursor(channel_id, after).await?;
+            let mut messages = self.messages_after(channel_id, &cursor, limit + 1, viewer_id).await?;
+            let next_cursor = Self::take_page(&mut messages, limit);
+            messages.reverse();
+
+            return Ok(MessagePage { messages, next_cursor });
+        }
+
+        if let Some(around) = request.around {
+            let cursor = self.get_cursor(channel_id, around).await?;
+            let mut newer = self.messages_after(channel_id, &cursor, limit / 2, viewer_id).await?;
+            newer.reverse();
+
+            let mut messages = newer;
+            if !cursor.deleted {
+                messages.push(self.get_message(cursor.id, Some(viewer_id)).await?);
+            }
+
+            let older_limit = limit - messages.len() as i64;
+            let mut older = self.messages_before(channel_id, &cursor, older_limit + 1, viewer_id).await?;
+            let next_cursor = match older_limit {
+                0 => messages.last().filter(|_| !older.is_empty()).map(|message| message.id),
+                _ => Self::take_page(&mut older, older_limit),
+            };
+            older.truncate(older_limit as usize);
+            messages.append(&mut older);
+
+            return Ok(MessagePage { messages, next_cursor });
+        }
+
+        let mut messages = match request.before {
+            Some(before) => {
+                let cursor = self.get_cursor(channel_id, before).await?;
+                self.messages_before(channel_id, &cursor, limit + 1, viewer_id).await?
+            }
+            None => self.latest_messages(channel_id, limit + 1, viewer_id).await?,
+        };
+        let next_cursor = Self::take_page(&mut messages, limit);
+
+        Ok(MessagePage { messages, next_cursor })
+    }
+
+    /// Searches the messages of every channel the caller can read, newest first.
+    pub async fn search_messages(&self, request: SearchMessagesRequest, user_id: Uuid) -> Result<SearchResults, AppError> {
+        let query = request.query.trim();
+        if query.is_empty() || query.len() > MAX_SEARCH_QUERY_LENGTH {
+            return Err(AppError::BadRequest(format!(
+                "Search query must be between 1 and {} characters", MAX_SEARCH_QUERY_LENGTH
+            )));
+        }
+
+        let limit = request.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        if !(1..=MAX_HISTORY_LIMIT).contains(&limit) {
+            return Err(AppError::BadRequest(format!("Limit must be between 1 and {}", MAX_HISTORY_LIMIT)));
+        }
+
+        let offset = request.offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(AppError::BadRequest("Offset cannot be negative".to_string()));
+        }
+
+        let channel_ids = self.permissions
+            .channels_with(user_id, Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY)
+            .await?;
+
+        // The count comes from its own subquery so it stays right when `offset` is past
+        // the last hit; the page is joined to it and is empty in that case.
+        let rows = sqlx::query_as!(SearchRow,
+            r#"
+                WITH hits AS (
+                    SELECT m.id, m.content, m.created_at
+                    FROM messages m
+                    INNER JOIN channels c ON c.id = m.channel_id
+                    WHERE m.channel_id = ANY($1)
+                        AND m.content_tsv @@ websearch_to_tsquery('english', $2)
+                        AND m.deleted_at IS NULL
+                        AND ($3::uuid IS NULL OR c.guild_id = $3)
+                        AND ($4::uuid IS NULL OR m.channel_id = $4)
+                        AND ($5::uuid IS NULL OR m.author_id = $5)
+                        AND ($6::timestamptz IS NULL OR m.created_at >= $6)
+                        AND ($7::timestamptz IS NULL OR m.created_at < $7)
+                        AND ($8::bool IS NULL OR (m.attachments IS NOT NULL) = $8)
+                )
+                SELECT
+                    page.id as "id?",
+                    ts_headline(
+                        'english', page.content, websearch_to_tsquery('english', $2),
+                        'StartSel=**, StopSel=**, HighlightAll=TRUE'
+                    ) as "highlight?",
+                    counted.total as "total!"
+                FROM (SELECT COUNT(*) as total FROM hits) counted
+                LEFT JOIN LATERAL (
+                    SELECT id, content, created_at
+                    FROM hits
+                    ORDER BY created_at DESC, id DESC
+                    LIMIT $9 OFFSET $10
+                ) page ON TRUE
+                ORDER BY page.created_at DESC, page.id DESC
+            "#, &channel_ids, query, request.guild_id, request.channel_id, request.author_id,
+            request.after, request.before, request.has_attachment, limit, offset
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let total = rows.first().map_or(0, |row| row.total);
+        let hits: Vec<(Uuid, String)> = rows
+            .into_iter()
+            .filter_map(|row| Some((row.id?, row.highlight.unwrap_or_default())))
+            .collect();
+        let message_ids: Vec<Uuid> = hits.iter().map(|(id, _)| *id).collect();
+        let mut highlights: HashMap<Uuid, String> = hits.into_iter().collect();
+
+        let results = self.load_messages(&message_ids, Some(user_id))
+            .await?
+            .into_iter()
+            .map(|message| SearchResult {
+                highlight: highlights.remove(&message.id).unwrap_or_default(),
+                message,
+            })
+            .collect();
+
+        Ok(SearchResults { total, results })
+    }
+
+    /// Truncates `messages` to `limit` and returns the id of the last kept message
+    /// if there were more.
+    fn take_page(messages: &mut Vec<Message>, limit: i64) -> Option<Uuid> {
+        let limit = limit as usize;
+        if messages.len() <= limit {
+            return None;
+        }
+
+        messages.truncate(limit);
+        messages.last().map(|message| message.id)
+    }
+
+    /// Looks up the position of a cursor message. Deleted messages still work as cursors
+    /// so clients can keep paging after a message they hold is removed.
+    async fn get_cursor(&self, channel_id: Uuid, message_id: Uuid) -> Result<HistoryCursor, AppError> {
+        sqlx::query_as!(HistoryCursor,
+            r#"
+                SELECT id, created_at, deleted_at IS NOT NULL as "deleted!"
+                FROM messages
+                WHERE id = $1 AND channel_id = $2
+            "#, message_id, channel_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Message not found".to_string()))
+    }
+
+    /// Fetches messages with their reactions as seen by `viewer_id`, in the order of
+    /// `message_ids`. This is the only query that builds [`Message`]s; listings select the
+    /// ids they want and load them here. Deleted messages are skipped.
+    async fn load_messages(&self, message_ids: &[Uuid], viewer_id: Option<Uuid>) -> Result<Vec<Message>, AppError> {
+        sqlx::query_as!(Message,
+            r#"
+                SELECT
+                    m.id,
+                    m.channel_id,
+                    m.author_id,
+                    m.content,
+                    m.message_type as "message_type: MessageType",
+                    m.attachments as "attachments: Json<Vec<Attachment>>",
+                    m.created_at,
+                    m.updated_at,
+                    m.reply_to,
+                    m.pinned_at,
+                    CASE WHEN p.id IS NOT NULL THEN json_build_object(
+                        'id', p.id,
+                        'author_id', p.author_id,
+                        'content', CASE WHEN p.deleted_at IS NULL THEN LEFT(p.content, 100) ELSE '' END,
+                        'deleted', p.deleted_at IS NOT NULL
+                    ) END as "referenced_message: Json<MessagePreview>",
+                    COALESCE((
+                        SELECT json_agg(json_build_object('emoji', r.emoji, 'count', r.count, 'me', r.me) ORDER BY r.first_reacted_at)
+                        FROM (
+                            SELECT
+                                emoji,
+                                COUNT(*) as count,
+                                COALESCE(BOOL_OR(user_id = $2), FALSE) as me,
+                                MIN(created_at) as first_reacted_at
+                            FROM message_reactions
+                            WHERE message_id = m.id
+                            GROUP BY emoji
+                        ) r
+                    ), '[]') as "reactions!: Json<Vec<ReactionCount>>",
+                    ARRAY(
+                        SELECT user_id FROM message_mentions WHERE message_id = m.id
+                    ) as "mentions!",
+                    ARRAY(
+                        SELECT role_id FROM message_role_mentions WHERE message_id = m.id
+                    ) as "mention_roles!",
+                    m.mention_everyone,
+                    (SELECT channel_id FROM threads WHERE starter_message_id = m.id) as thread_id
+                FROM UNNEST($1::uuid[]) WITH ORDINALITY as ids(id, position)
+                INNER JOIN messages m ON m.id = ids.id
+                LEFT JOIN messages p ON p.id = m.reply_to
+                WHERE m.deleted_at IS NULL
+                ORDER BY ids.position
+            "#, message_ids, viewer_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))
+    }
+
+    /// Fetches a message as seen by `viewer_id`. Broadcast copies are fetched without a
+    /// viewer, so every `me` flag is false.
+    async fn get_message(&self, message_id: Uuid, viewer_id: Option<Uuid>) -> Result<Message, AppError> {
+        self.load_messages(&[message_id], viewer_id)
+            .await?
+            .pop()
+            .ok_or(AppError::NotFound("Message not found".to_string()))
+    }
+
+    async fn latest_messages(&self, channel_id: Uuid, limit: i64, viewer_id: Uuid) -> Result<Vec<Message>, AppError> {
+        let message_ids = sqlx::query_scalar!(
+            r#"
+                SELECT id
+                FROM messages
+                WHERE channel_id = $1 AND deleted_at IS NULL
+                ORDER BY created_at DESC, id DESC
+                LIMIT $2
+            "#, channel_id, limit
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.load_messages(&message_ids, Some(viewer_id)).await
+    }
+
+    /// Messages older than `cursor`, newest first.
+    async fn messages_before(&self, channel_id: Uuid, cursor: &HistoryCursor, limit: i64, viewer_id: Uuid) -> Result<Vec<Message>, AppError> {
+        let message_ids = sqlx::query_scalar!(
+            r#"
+                SELECT id
+                FROM messages
+                WHERE channel_id = $1 AND (created_at, id) < ($2, $3) AND deleted_at IS NULL
+                ORDER BY created_at DESC, id DESC
+                LIMIT $4
+            "#, channel_id, cursor.created_at, cursor.id, limit
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.load_messages(&message_ids, Some(viewer_id)).await
+    }
+
+    /// Messages newer than `cursor`, oldest first.
+    async fn messages_after(&self, channel_id: Uuid, cursor: &HistoryCursor, limit: i64, viewer_id: Uuid) -> Result<Vec<Message>, AppError> {
+        let message_ids = sqlx::query_scalar!(
+            r#"
+                SELECT id
+                FROM messages
+                WHERE channel_id = $1 AND (created_at, id) > ($2, $3) AND deleted_at IS NULL
+                ORDER BY created_at ASC, id ASC
+                LIMIT $4
+            "#, channel_id, cursor.created_at, cursor.id, limit
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.load_messages(&message_ids, Some(viewer_id)).await
     }
 }
\ No newline at end of file
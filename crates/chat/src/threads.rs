@@ -0,0 +1,492 @@
+use std::sync::Arc;
+use std::time::Duration;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use blazing_models::{
+    AppError, ChannelType, CreateThreadRequest, Permissions, Thread, ThreadMember, UpdateThreadRequest,
+};
+use blazing_ws::{Broadcaster, ClientId, SessionRegistry};
+use crate::{PermissionsService, WsMessage};
+
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+const DEFAULT_AUTO_ARCHIVE_DURATION: i32 = 1440;
+/// Allowed inactivity periods before a thread is archived, in minutes: an hour, a day,
+/// three days and a week.
+const AUTO_ARCHIVE_DURATIONS: [i32; 4] = [60, 1440, 4320, 10080];
+const AUTO_ARCHIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What posting a message changed about the thread it was posted in.
+pub(crate) struct ThreadActivity {
+    pub(crate) joined: bool,
+    pub(crate) unarchived: bool,
+}
+
+/// Bumps the activity of `channel_id` if it is a thread, unarchiving it and adding
+/// `user_id` as a member. Returns `None` for regular channels.
+pub(crate) async fn record_thread_activity(
+    conn: &mut PgConnection,
+    channel_id: Uuid,
+    user_id: Uuid
+) -> Result<Option<ThreadActivity>, AppError> {
+    let unarchived = sqlx::query_scalar!(
+        r#"
+            UPDATE threads t
+            SET last_activity_at = NOW(), archived_at = NULL
+            FROM (SELECT channel_id, archived_at FROM threads WHERE channel_id = $1 FOR UPDATE) previous
+            WHERE t.channel_id = previous.channel_id
+            RETURNING previous.archived_at IS NOT NULL as "unarchived!"
+        "#, channel_id
+    )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+    let Some(unarchived) = unarchived else {
+        return Ok(None);
+    };
+
+    let joined = sqlx::query!(
+        "INSERT INTO thread_members (thread_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        channel_id, user_id
+    )
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+        .rows_affected() > 0;
+
+    Ok(Some(ThreadActivity { joined, unarchived }))
+}
+
+pub(crate) async fn fetch_thread(db_pool: &PgPool, thread_id: Uuid) -> Result<Thread, AppError> {
+    sqlx::query_as!(Thread,
+        r#"
+            SELECT
+                c.id,
+                c.guild_id as "guild_id!",
+                c.parent_id as "parent_id!",
+                c.name,
+                t.owner_id,
+                t.starter_message_id,
+                (SELECT COUNT(*) FROM thread_members WHERE thread_id = c.id) as "member_count!",
+                (SELECT COUNT(*) FROM messages WHERE channel_id = c.id AND deleted_at IS NULL) as "message_count!",
+                t.auto_archive_duration,
+                t.archived_at,
+                t.last_activity_at,
+                c.created_at
+            FROM channels c
+            INNER JOIN threads t ON t.channel_id = c.id
+            WHERE c.id = $1
+        "#, thread_id
+    )
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+        .ok_or(AppError::NotFound("Thread not found".to_string()))
+}
+
+/// Threads branch off a guild channel, either from one of its messages or on their own.
+/// A thread is a channel with its parent's permissions, so its messages go through
+/// [`crate::MessagesService`] like any other. Sessions only receive a thread's traffic
+/// while their user is a member of it, or while the session is viewing it.
+pub struct ThreadsService {
+    db_pool: PgPool,
+    permissions: Arc<PermissionsService>,
+    broadcaster: Arc<Broadcaster<Uuid, WsMessage>>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl ThreadsService {
+    pub fn new(
+        db_pool: PgPool,
+        permissions: Arc<PermissionsService>,
+        broadcaster: Arc<Broadcaster<Uuid, WsMessage>>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, permissions, broadcaster, sessions }
+    }
+
+    fn validate_name(name: &str) -> Result<String, AppError> {
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(AppError::BadRequest("Thread name cannot be empty".to_string()));
+        }
+        if name.chars().count() > MAX_THREAD_NAME_LENGTH {
+            return Err(AppError::BadRequest("Thread name too long".to_string()));
+        }
+
+        Ok(name.to_string())
+    }
+
+    fn validate_auto_archive_duration(duration: i32) -> Result<i32, AppError> {
+        if !AUTO_ARCHIVE_DURATIONS.contains(&duration) {
+            return Err(AppError::BadRequest(format!(
+                "Auto archive duration must be one of {:?} minutes", AUTO_ARCHIVE_DURATIONS
+            )));
+        }
+        Ok(duration)
+    }
+
+    /// Starts a thread in `channel_id`, from `message_id` if given. Requires SEND_MESSAGES
+    /// in the channel; a message can only start one thread.
+    pub async fn create_thread(
+        &self,
+        channel_id: Uuid,
+        message_id: Option<Uuid>,
+        request: CreateThreadRequest,
+        user_id: Uuid
+    ) -> Result<Thread, AppError> {
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::SEND_MESSAGES)
+            .await?;
+
+        let name = Self::validate_name(&request.name)?;
+        let auto_archive_duration = Self::validate_auto_archive_duration(
+            request.auto_archive_duration.unwrap_or(DEFAULT_AUTO_ARCHIVE_DURATION)
+        )?;
+
+        let parent = sqlx::query!(
+            r#"SELECT guild_id, type as "channel_type: ChannelType" FROM channels WHERE id = $1"#,
+            channel_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let guild_id = match (parent.guild_id, parent.channel_type) {
+            (Some(guild_id), ChannelType::Text | ChannelType::Announcement) => guild_id,
+            _ => return Err(AppError::BadRequest("Threads can only be created in guild text channels".to_string())),
+        };
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if let Some(message_id) = message_id {
+            // Locks the starter message so two threads cannot be started from it at once.
+            let has_thread = sqlx::query_scalar!(
+                r#"
+                    SELECT EXISTS(
+                        SELECT 1 FROM threads WHERE starter_message_id = m.id
+                    ) as "exists!"
+                    FROM messages m
+                    WHERE m.id = $1 AND m.channel_id = $2 AND m.deleted_at IS NULL
+                    FOR UPDATE
+                "#, message_id, channel_id
+            )
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+                .ok_or(AppError::NotFound("Message not found".to_string()))?;
+
+            if has_thread {
+                return Err(AppError::BadRequest("A thread has already been started from this message".to_string()));
+            }
+        }
+
+        let thread_id = sqlx::query_scalar!(
+            r#"
+                INSERT INTO channels (guild_id, name, type, parent_id)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id
+            "#, guild_id, name, ChannelType::Thread as ChannelType, channel_id
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create thread: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO threads (channel_id, owner_id, starter_message_id, auto_archive_duration)
+                VALUES ($1, $2, $3, $4)
+            "#, thread_id, user_id, message_id, auto_archive_duration
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create thread: {}", e)))?;
+
+        sqlx::query!(
+            "INSERT INTO thread_members (thread_id, user_id) VALUES ($1, $2)",
+            thread_id, user_id
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create thread: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let thread = fetch_thread(&self.db_pool, thread_id).await?;
+
+        self.sessions.subscribe_users(&[user_id], &[thread_id]).await;
+        if let Err(e) = self.broadcaster.broadcast(
+            &channel_id,
+            WsMessage::ThreadCreated { thread: thread.clone() }
+        ).await {
+            tracing::warn!("Failed to broadcast thread: {}", e);
+        }
+
+        Ok(thread)
+    }
+
+    /// Active or archived threads of a channel, most recently active first.
+    pub async fn get_threads(&self, channel_id: Uuid, archived: bool, user_id: Uuid) -> Result<Vec<Thread>, AppError> {
+        self.permissions
+            .require_channel_permissions(user_id, channel_id, Permissions::READ_MESSAGE_HISTORY)
+            .await?;
+
+        let threads = sqlx::query_as!(Thread,
+            r#"
+                SELECT
+                    c.id,
+                    c.guild_id as "guild_id!",
+                    c.parent_id as "parent_id!",
+                    c.name,
+                    t.owner_id,
+                    t.starter_message_id,
+                    (SELECT COUNT(*) FROM thread_members WHERE thread_id = c.id) as "member_count!",
+                    (SELECT COUNT(*) FROM messages WHERE channel_id = c.id AND deleted_at IS NULL) as "message_count!",
+                    t.auto_archive_duration,
+                    t.archived_at,
+                    t.last_activity_at,
+                    c.created_at
+                FROM channels c
+                INNER JOIN threads t ON t.channel_id = c.id
+                WHERE c.parent_id = $1 AND (t.archived_at IS NOT NULL) = $2
+                ORDER BY t.last_activity_at DESC
+            "#, channel_id, archived
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(threads)
+    }
+
+    pub async fn get_thread(&self, thread_id: Uuid, user_id: Uuid) -> Result<Thread, AppError> {
+        self.require_thread(thread_id, user_id).await?;
+
+        fetch_thread(&self.db_pool, thread_id).await
+    }
+
+    async fn require_thread(&self, thread_id: Uuid, user_id: Uuid) -> Result<Permissions, AppError> {
+        let permissions = self.permissions
+            .require_channel_permissions(user_id, thread_id, Permissions::empty())
+            .await
+            .map_err(|_| AppError::NotFound("Thread not found".to_string()))?;
+
+        let is_thread = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM threads WHERE channel_id = $1) as "exists!""#,
+            thread_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if !is_thread {
+            return Err(AppError::NotFound("Thread not found".to_string()));
+        }
+
+        Ok(permissions)
+    }
+
+    /// Renames, archives or unarchives a thread. Allowed for its owner and for anyone who
+    /// can manage the parent channel.
+    pub async fn update_thread(
+        &self,
+        thread_id: Uuid,
+        request: UpdateThreadRequest,
+        user_id: Uuid
+    ) -> Result<Thread, AppError> {
+        let permissions = self.require_thread(thread_id, user_id).await?;
+        let thread = fetch_thread(&self.db_pool, thread_id).await?;
+
+        if thread.owner_id != user_id && !permissions.contains(Permissions::MANAGE_CHANNELS) {
+            return Err(AppError::Forbidden("Missing permissions".to_string()));
+        }
+
+        let name = request.name.as_deref().map(Self::validate_name).transpose()?;
+        let auto_archive_duration = request.auto_archive_duration
+            .map(Self::validate_auto_archive_duration)
+            .transpose()?;
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if let Some(name) = name {
+            sqlx::query!("UPDATE channels SET name = $2 WHERE id = $1", thread_id, name)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to update thread: {}", e)))?;
+        }
+
+        // Unarchiving counts as activity, so the thread is not archived again right away.
+        sqlx::query!(
+            r#"
+                UPDATE threads
+                SET
+                    auto_archive_duration = COALESCE($2, auto_archive_duration),
+                    archived_at = CASE
+                        WHEN $3::bool IS NULL THEN archived_at
+                        WHEN $3 THEN COALESCE(archived_at, NOW())
+                    END,
+                    last_activity_at = CASE WHEN $3 = FALSE THEN NOW() ELSE last_activity_at END
+                WHERE channel_id = $1
+            "#, thread_id, auto_archive_duration, request.archived
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update thread: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let thread = fetch_thread(&self.db_pool, thread_id).await?;
+        self.broadcast_thread_update(&thread).await;
+
+        Ok(thread)
+    }
+
+    async fn broadcast_thread_update(&self, thread: &Thread) {
+        if let Err(e) = self.broadcaster.broadcast(
+            &thread.parent_id,
+            WsMessage::ThreadUpdated { thread: thread.clone() }
+        ).await {
+            tracing::warn!("Failed to broadcast thread update: {}", e);
+        }
+    }
+
+    pub async fn get_members(&self, thread_id: Uuid, user_id: Uuid) -> Result<Vec<ThreadMember>, AppError> {
+        self.require_thread(thread_id, user_id).await?;
+
+        let members = sqlx::query_as!(ThreadMember,
+            "SELECT user_id, joined_at FROM thread_members WHERE thread_id = $1 ORDER BY joined_at",
+            thread_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(members)
+    }
+
+    pub async fn join_thread(&self, thread_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.require_thread(thread_id, user_id).await?;
+
+        let result = sqlx::query!(
+            "INSERT INTO thread_members (thread_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            thread_id, user_id
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to join thread: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        self.sessions.subscribe_users(&[user_id], &[thread_id]).await;
+        self.broadcast_members_update(thread_id, user_id, true).await;
+
+        Ok(())
+    }
+
+    /// Leaves a thread. This also stops every session of the user from following it,
+    /// including sessions that were viewing it.
+    pub async fn leave_thread(&self, thread_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM thread_members WHERE thread_id = $1 AND user_id = $2",
+            thread_id, user_id
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to leave thread: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Thread not found".to_string()));
+        }
+
+        self.sessions.unsubscribe_users(&[user_id], &[thread_id]).await;
+        self.broadcast_members_update(thread_id, user_id, false).await;
+
+        Ok(())
+    }
+
+    async fn broadcast_members_update(&self, thread_id: Uuid, user_id: Uuid, joined: bool) {
+        if let Err(e) = self.broadcaster.broadcast(
+            &thread_id,
+            WsMessage::ThreadMembersUpdate { thread_id, user_id, joined }
+        ).await {
+            tracing::warn!("Failed to broadcast thread members update: {}", e);
+        }
+    }
+
+    /// Starts sending the thread's traffic to a single session without joining it.
+    pub async fn view_thread(&self, thread_id: Uuid, user_id: Uuid, client_id: ClientId) -> Result<(), AppError> {
+        self.require_thread(thread_id, user_id).await?;
+        self.sessions.subscribe_client(user_id, client_id, &[thread_id]).await;
+
+        Ok(())
+    }
+
+    /// Stops sending the thread's traffic to a session, unless its user is a member.
+    pub async fn stop_viewing_thread(&self, thread_id: Uuid, user_id: Uuid, client_id: ClientId) -> Result<(), AppError> {
+        let is_member = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM thread_members WHERE thread_id = $1 AND user_id = $2
+                ) as "exists!"
+            "#, thread_id, user_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if !is_member {
+            self.sessions.unsubscribe_client(user_id, client_id, &[thread_id]).await;
+        }
+
+        Ok(())
+    }
+
+    /// Archives every thread that has been inactive for longer than its auto archive duration.
+    pub async fn archive_inactive_threads(&self) -> Result<(), AppError> {
+        let thread_ids = sqlx::query_scalar!(
+            r#"
+                UPDATE threads
+                SET archived_at = NOW()
+                WHERE archived_at IS NULL
+                    AND last_activity_at + auto_archive_duration * INTERVAL '1 minute' <= NOW()
+                RETURNING channel_id
+            "#
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to archive threads: {}", e)))?;
+
+        for thread_id in thread_ids {
+            let thread = fetch_thread(&self.db_pool, thread_id).await?;
+            self.broadcast_thread_update(&thread).await;
+        }
+
+        Ok(())
+    }
+
+    /// Runs [`Self::archive_inactive_threads`] periodically until the task is dropped.
+    pub async fn run_auto_archive(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(AUTO_ARCHIVE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.archive_inactive_threads().await {
+                tracing::warn!("Failed to archive inactive threads: {}", e);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -1,12 +1,18 @@
 use async_trait::async_trait;
-use blazing_models::{Message, SendMessageRequest};
-use blazing_ws::{MessageHandler, ClientId, Result};
+use blazing_models::{AppError, DmChannel, Message, Permissions, Relationship, SendMessageRequest, Thread};
+use blazing_ws::{MessageHandler, MessageError, MessageOutcome, ClientId, Result, INTERNAL_ERROR, INVALID_MESSAGE};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 use blazing_auth::validate_token;
-use crate::MessagesService;
+use crate::{validate_content, MessagesService, ThreadsService};
 
+/// Events exchanged over the chat WebSocket, tagged by `type`.
+///
+/// Client messages are answered with `ack` and `error` frames rather than events (see
+/// [`blazing_ws::MessageError`]). A message refused by slow mode or the flood limit gets
+/// an `error` frame with `code: "rate_limited"` and `retry_after` in seconds; it replaces
+/// the `rate_limited` event that earlier clients listened for.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum WsMessage {
@@ -16,6 +22,108 @@ pub enum WsMessage {
     #[serde(rename = "message_created")]
     MessageCreated { message: Message },
 
+    #[serde(rename = "message_update")]
+    UpdateMessage {
+        message_id: Uuid,
+        content: String
+    },
+
+    #[serde(rename = "message_updated")]
+    MessageUpdated { message: Message },
+
+    #[serde(rename = "reaction_add")]
+    AddReaction {
+        message_id: Uuid,
+        emoji: String
+    },
+
+    #[serde(rename = "reaction_remove")]
+    RemoveReaction {
+        message_id: Uuid,
+        emoji: String
+    },
+
+    #[serde(rename = "reaction_added")]
+    ReactionAdded {
+        channel_id: Uuid,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String
+    },
+
+    #[serde(rename = "reaction_removed")]
+    ReactionRemoved {
+        channel_id: Uuid,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String
+    },
+
+    #[serde(rename = "channel_pins_update")]
+    ChannelPinsUpdate {
+        channel_id: Uuid,
+        message_id: Uuid,
+        pinned: bool
+    },
+
+    #[serde(rename = "ack")]
+    AckMessage {
+        channel_id: Uuid,
+        message_id: Uuid
+    },
+
+    /// Sent to every session of the user who acknowledged the message.
+    #[serde(rename = "message_ack")]
+    MessageAcked {
+        channel_id: Uuid,
+        message_id: Uuid
+    },
+
+    /// Sent to every recipient of a newly created DM.
+    #[serde(rename = "dm_channel_created")]
+    DmChannelCreated { channel: DmChannel },
+
+    /// Sent to a user when their relationship with another user is created or changes.
+    #[serde(rename = "relationship_add")]
+    RelationshipAdded { relationship: Relationship },
+
+    /// Sent to a user when their relationship with `user_id` is removed.
+    #[serde(rename = "relationship_remove")]
+    RelationshipRemoved { user_id: Uuid },
+
+    /// Sent to the parent channel when a thread is started in it.
+    #[serde(rename = "thread_created")]
+    ThreadCreated { thread: Thread },
+
+    /// Sent to the parent channel when a thread is renamed, archived or unarchived.
+    #[serde(rename = "thread_updated")]
+    ThreadUpdated { thread: Thread },
+
+    #[serde(rename = "thread_members_update")]
+    ThreadMembersUpdate {
+        thread_id: Uuid,
+        user_id: Uuid,
+        joined: bool
+    },
+
+    /// Follows a thread from this session only, without joining it.
+    #[serde(rename = "thread_subscribe")]
+    SubscribeThread { thread_id: Uuid },
+
+    #[serde(rename = "thread_unsubscribe")]
+    UnsubscribeThread { thread_id: Uuid },
+
+    /// Sent to a session that fell behind and missed events in `channel_ids`. The client
+    /// should re-fetch their recent messages.
+    #[serde(rename = "resync_required")]
+    ResyncRequired { channel_ids: Vec<Uuid> },
+
+    #[serde(rename = "message_deleted")]
+    MessageDeleted {
+        channel_id: Uuid,
+        message_id: Uuid
+    },
+
     #[serde(rename = "typing_start")]
     TypingStart {
         channel_id: Uuid,
@@ -32,16 +140,49 @@ pub enum WsMessage {
 #[derive(Clone)]
 pub struct ChatMessageHandler {
     messages_service: Arc<MessagesService>,
+    threads_service: Arc<ThreadsService>,
     jwt_secret: String,
 }
 
 impl ChatMessageHandler {
-    pub fn new(messages_service: Arc<MessagesService>, jwt_secret: String) -> Self {
+    pub fn new(
+        messages_service: Arc<MessagesService>,
+        threads_service: Arc<ThreadsService>,
+        jwt_secret: String
+    ) -> Self {
         Self {
             messages_service,
+            threads_service,
             jwt_secret,
         }
     }
+
+    async fn require_send_messages(&self, user_id: Uuid, channel_id: Uuid) -> Result<()> {
+        self.messages_service
+            .get_permissions()
+            .require_channel_permissions(user_id, channel_id, Permissions::SEND_MESSAGES)
+            .await
+            .map_err(message_error)?;
+
+        Ok(())
+    }
+}
+
+/// The `error` frame sent back for a client message refused with `error`.
+fn message_error(error: AppError) -> MessageError {
+    match error {
+        AppError::BadRequest(message) => MessageError::new("bad_request", message),
+        AppError::Unauthorized(message) => MessageError::new("unauthorized", message),
+        AppError::Forbidden(message) => MessageError::new("forbidden", message),
+        AppError::NotFound(message) => MessageError::new("not_found", message),
+        AppError::RateLimited { message, retry_after } => {
+            MessageError::new("rate_limited", message).with_retry_after(retry_after)
+        }
+        AppError::Database(_) | AppError::Internal(_) => {
+            tracing::error!("Failed to handle WebSocket message: {}", error);
+            MessageError::new(INTERNAL_ERROR, "Internal server error")
+        }
+    }
 }
 
 #[async_trait]
@@ -66,66 +207,114 @@ impl MessageHandler for ChatMessageHandler {
 
     async fn on_message(
         &self,
-        _client_id: ClientId,
+        client_id: ClientId,
         user_id: Uuid,
         message: Self::Message,
-    ) -> Result<Option<(Self::BroadcastKey, Self::Message)>> {
+    ) -> Result<MessageOutcome<Self::BroadcastKey, Self::Message>> {
         match message {
             WsMessage::NewMessage(request) => {
-                let channel_id = request.channel_id;
-
-                let created_message = self.messages_service
+                // `create_message` already broadcasts `MessageCreated` to the channel.
+                let message = self.messages_service
                     .create_message(request, user_id)
                     .await
-                    .map_err(|e| format!("Failed to create message: {}", e))?;
+                    .map_err(message_error)?;
 
-                Ok(Some((
-                    channel_id,
-                    WsMessage::MessageCreated { message: created_message }
-                )))
+                Ok(MessageOutcome::reply(WsMessage::MessageCreated { message }))
             }
 
-            WsMessage::TypingStart { channel_id, user_id } => {
-                Ok(Some((channel_id, WsMessage::TypingStart { channel_id, user_id })))
+            WsMessage::UpdateMessage { message_id, content } => {
+                // `update_message` broadcasts `MessageUpdated` to the channel.
+                let message = self.messages_service
+                    .update_message(message_id, content, user_id)
+                    .await
+                    .map_err(message_error)?;
+
+                Ok(MessageOutcome::reply(WsMessage::MessageUpdated { message }))
             }
 
-            WsMessage::TypingStop { channel_id, user_id } => {
-                Ok(Some((channel_id, WsMessage::TypingStop { channel_id, user_id })))
+            WsMessage::AddReaction { message_id, emoji } => {
+                self.messages_service
+                    .add_reaction(message_id, emoji, user_id)
+                    .await
+                    .map_err(message_error)?;
+
+                Ok(MessageOutcome::none())
+            }
+
+            WsMessage::RemoveReaction { message_id, emoji } => {
+                self.messages_service
+                    .remove_reaction(message_id, emoji, user_id)
+                    .await
+                    .map_err(message_error)?;
+
+                Ok(MessageOutcome::none())
             }
-            _ => Ok(None),
+
+            WsMessage::AckMessage { channel_id, message_id } => {
+                self.messages_service
+                    .ack_message(channel_id, message_id, user_id)
+                    .await
+                    .map_err(message_error)?;
+
+                Ok(MessageOutcome::none())
+            }
+
+            WsMessage::SubscribeThread { thread_id } => {
+                self.threads_service
+                    .view_thread(thread_id, user_id, client_id)
+                    .await
+                    .map_err(message_error)?;
+
+                Ok(MessageOutcome::none())
+            }
+
+            WsMessage::UnsubscribeThread { thread_id } => {
+                self.threads_service
+                    .stop_viewing_thread(thread_id, user_id, client_id)
+                    .await
+                    .map_err(message_error)?;
+
+                Ok(MessageOutcome::none())
+            }
+
+            WsMessage::TypingStart { channel_id, .. } => {
+                self.require_send_messages(user_id, channel_id).await?;
+                Ok(MessageOutcome::broadcast(channel_id, WsMessage::TypingStart { channel_id, user_id }))
+            }
+
+            WsMessage::TypingStop { channel_id, .. } => {
+                self.require_send_messages(user_id, channel_id).await?;
+                Ok(MessageOutcome::broadcast(channel_id, WsMessage::TypingStop { channel_id, user_id }))
+            }
+            _ => Err(MessageError::new(INVALID_MESSAGE, "This event cannot be sent by clients").into()),
         }
     }
 
     async fn get_user_broadcast_keys(&self, user_id: Uuid) -> Result<Vec<Self::BroadcastKey>> {
-        let channels = sqlx::query!(
-            r#"
-                SELECT DISTINCT c.id
-                FROM channels c
-                INNER JOIN guild_members gm ON c.guild_id = gm.guild_id
-                WHERE gm.user_id = $1
-            "#,
-            user_id
-        )
-            .fetch_all(self.messages_service.get_pool())
-            .await
-            .map_err(|e| format!("Database error: {}", e))?;
+        let permissions = self.messages_service.get_permissions();
+        let visible = permissions.visible_channels(user_id).await?;
+        let channel_ids = permissions.subscribable_channels(user_id, &visible).await?;
 
-        let channel_ids: Vec<Uuid> = channels.into_iter().map(|row| row.id).collect();
         tracing::info!("User {} subscribed to channels: {:?}", user_id, channel_ids);
+
         Ok(channel_ids)
     }
 
+    async fn on_lagged(
+        &self,
+        client_id: ClientId,
+        user_id: Uuid,
+        keys: Vec<Self::BroadcastKey>,
+    ) -> Result<Option<Self::Message>> {
+        tracing::warn!("Client {} of user {} missed events on {:?}", client_id, user_id, keys);
+
+        Ok(Some(WsMessage::ResyncRequired { channel_ids: keys }))
+    }
+
     async fn validate_message(&self, message: &Self::Message) -> Result<()> {
         match message {
-            WsMessage::NewMessage(request) => {
-                if request.content.trim().is_empty() {
-                    return Err("Message content cannot be empty".into());
-                }
-                if request.content.len() > 2000 {
-                    return Err("Message too long".into());
-                }
-                Ok(())
-            }
+            WsMessage::NewMessage(request) => Ok(validate_content(&request.content).map_err(message_error)?),
+            WsMessage::UpdateMessage { content, .. } => Ok(validate_content(content).map_err(message_error)?),
             _ => Ok(())
         }
     }
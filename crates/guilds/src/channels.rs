@@ -0,0 +1,475 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use blazing_chat::{PermissionsService, WsMessage};
+use blazing_models::{
+    AppError, Channel, ChannelType, CreateChannelRequest, OverwriteType, PermissionOverwrite, Permissions,
+    ReorderChannelsRequest, SetOverwriteRequest, UpdateChannelRequest,
+};
+use blazing_ws::SessionRegistry;
+
+const MAX_CHANNEL_NAME_LENGTH: usize = 100;
+const MAX_SLOWMODE_SECONDS: i32 = 21600;
+
+pub struct ChannelsService {
+    db_pool: PgPool,
+    permissions: Arc<PermissionsService>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl ChannelsService {
+    pub fn new(
+        db_pool: PgPool,
+        permissions: Arc<PermissionsService>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, permissions, sessions }
+    }
+
+    fn validate_name(name: &str) -> Result<String, AppError> {
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(AppError::BadRequest("Channel name cannot be empty".to_string()));
+        }
+        if name.chars().count() > MAX_CHANNEL_NAME_LENGTH {
+            return Err(AppError::BadRequest("Channel name too long".to_string()));
+        }
+
+        Ok(name.to_string())
+    }
+
+    async fn require_manage_channels(&self, guild_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.permissions
+            .require_guild_permissions(user_id, guild_id, Permissions::MANAGE_CHANNELS)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_channel(
+        &self,
+        guild_id: Uuid,
+        request: CreateChannelRequest,
+        user_id: Uuid
+    ) -> Result<Channel, AppError> {
+        self.require_manage_channels(guild_id, user_id).await?;
+
+        let name = Self::validate_name(&request.name)?;
+        let channel_type = request.channel_type.unwrap_or(ChannelType::Text);
+        if channel_type.is_dm() {
+            return Err(AppError::BadRequest("Direct message channels cannot be created in a guild".to_string()));
+        }
+        if channel_type == ChannelType::Thread {
+            return Err(AppError::BadRequest("Threads are created from a channel".to_string()));
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let channel = sqlx::query_as!(Channel,
+            r#"
+                INSERT INTO channels (guild_id, name, type, position)
+                VALUES ($1, $2, $3, (
+                    SELECT COALESCE(MAX(position) + 1, 0) FROM channels WHERE guild_id = $1 AND parent_id IS NULL
+                ))
+                RETURNING id, guild_id as "guild_id!", name, type as "channel_type: ChannelType", position, slowmode_seconds, created_at
+            "#, guild_id, name, channel_type as ChannelType
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create channel: {}", e)))?;
+
+        if channel_type == ChannelType::Announcement {
+            // Announcement channels are read-only for regular members.
+            sqlx::query!(
+                r#"
+                    INSERT INTO channel_overwrites (channel_id, target_type, target_id, allow, deny)
+                    SELECT $1, $2, id, 0, $3
+                    FROM roles
+                    WHERE guild_id = $4 AND is_default
+                "#, channel.id, OverwriteType::Role as OverwriteType,
+                Permissions::SEND_MESSAGES as Permissions, guild_id
+            )
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Failed to create channel: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let members = sqlx::query_scalar!(
+            "SELECT user_id FROM guild_members WHERE guild_id = $1",
+            guild_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let viewers: Vec<Uuid> = self.permissions
+            .visible_guild_channels(guild_id, &members)
+            .await?
+            .into_iter()
+            .filter(|(_, channel_ids)| channel_ids.contains(&channel.id))
+            .map(|(user_id, _)| user_id)
+            .collect();
+
+        let notified = self.sessions.subscribe_users(&viewers, &[channel.id]).await;
+        tracing::info!("Channel {} created in guild {}, subscribed {} sessions", channel.id, guild_id, notified);
+
+        Ok(channel)
+    }
+
+    pub async fn get_channels(&self, guild_id: Uuid, user_id: Uuid) -> Result<Vec<Channel>, AppError> {
+        let visible = self.permissions
+            .visible_guild_channels(guild_id, &[user_id])
+            .await?
+            .remove(&user_id)
+            .ok_or(AppError::NotFound("Guild not found".to_string()))?;
+
+        let channels = sqlx::query_as!(Channel,
+            r#"
+                SELECT id, guild_id as "guild_id!", name, type as "channel_type: ChannelType", position, slowmode_seconds, created_at
+                FROM channels
+                WHERE guild_id = $1 AND id = ANY($2) AND parent_id IS NULL
+                ORDER BY position, created_at
+            "#, guild_id, &visible
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(channels)
+    }
+
+    pub async fn update_channel(
+        &self,
+        guild_id: Uuid,
+        channel_id: Uuid,
+        request: UpdateChannelRequest,
+        user_id: Uuid
+    ) -> Result<Channel, AppError> {
+        self.require_manage_channels(guild_id, user_id).await?;
+
+        let name = request.name
+            .as_deref()
+            .map(Self::validate_name)
+            .transpose()?;
+
+        if let Some(slowmode_seconds) = request.slowmode_seconds
+            && !(0..=MAX_SLOWMODE_SECONDS).contains(&slowmode_seconds)
+        {
+            return Err(AppError::BadRequest(format!(
+                "Slow mode must be between 0 and {} seconds", MAX_SLOWMODE_SECONDS
+            )));
+        }
+
+        sqlx::query_as!(Channel,
+            r#"
+                UPDATE channels
+                SET name = COALESCE($3, name),
+                    slowmode_seconds = COALESCE($4, slowmode_seconds)
+                WHERE id = $1 AND guild_id = $2 AND parent_id IS NULL
+                RETURNING id, guild_id as "guild_id!", name, type as "channel_type: ChannelType", position, slowmode_seconds, created_at
+            "#, channel_id, guild_id, name, request.slowmode_seconds
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update channel: {}", e)))?
+            .ok_or(AppError::NotFound("Channel not found".to_string()))
+    }
+
+    pub async fn delete_channel(&self, guild_id: Uuid, channel_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.require_manage_channels(guild_id, user_id).await?;
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        // Threads of the channel are deleted with it.
+        let mut channel_ids = sqlx::query_scalar!(
+            "SELECT id FROM channels WHERE parent_id = $1 AND guild_id = $2",
+            channel_id, guild_id
+        )
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let result = sqlx::query!(
+            "DELETE FROM channels WHERE id = $1 AND guild_id = $2 AND parent_id IS NULL",
+            channel_id, guild_id
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete channel: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Channel not found".to_string()));
+        }
+
+        let members = sqlx::query_scalar!(
+            "SELECT user_id FROM guild_members WHERE guild_id = $1",
+            guild_id
+        )
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        channel_ids.push(channel_id);
+        self.sessions.unsubscribe_users(&members, &channel_ids).await;
+
+        Ok(())
+    }
+
+    pub async fn reorder_channels(
+        &self,
+        guild_id: Uuid,
+        request: ReorderChannelsRequest,
+        user_id: Uuid
+    ) -> Result<Vec<Channel>, AppError> {
+        self.require_manage_channels(guild_id, user_id).await?;
+
+        let (ids, positions): (Vec<Uuid>, Vec<i32>) = request.channels
+            .iter()
+            .map(|channel| (channel.id, channel.position))
+            .unzip();
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE channels c
+                SET position = v.position
+                FROM UNNEST($2::uuid[], $3::int4[]) AS v(id, position)
+                WHERE c.id = v.id AND c.guild_id = $1 AND c.parent_id IS NULL
+            "#, guild_id, &ids, &positions
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to reorder channels: {}", e)))?;
+
+        if result.rows_affected() != ids.len() as u64 {
+            return Err(AppError::BadRequest("Unknown or duplicate channel in reorder request".to_string()));
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.get_channels(guild_id, user_id).await
+    }
+
+    async fn require_guild_channel(&self, guild_id: Uuid, channel_id: Uuid) -> Result<(), AppError> {
+        let exists = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM channels WHERE id = $1 AND guild_id = $2 AND parent_id IS NULL
+                ) as "exists!"
+            "#, channel_id, guild_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if !exists {
+            return Err(AppError::NotFound("Channel not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_overwrites(
+        &self,
+        guild_id: Uuid,
+        channel_id: Uuid,
+        user_id: Uuid
+    ) -> Result<Vec<PermissionOverwrite>, AppError> {
+        self.require_manage_channels(guild_id, user_id).await?;
+        self.require_guild_channel(guild_id, channel_id).await?;
+
+        let overwrites = sqlx::query_as!(PermissionOverwrite,
+            r#"
+                SELECT
+                    channel_id,
+                    target_type as "target_type: OverwriteType",
+                    target_id,
+                    allow as "allow: Permissions",
+                    deny as "deny: Permissions"
+                FROM channel_overwrites
+                WHERE channel_id = $1
+            "#, channel_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(overwrites)
+    }
+
+    pub async fn set_overwrite(
+        &self,
+        guild_id: Uuid,
+        channel_id: Uuid,
+        target_id: Uuid,
+        request: SetOverwriteRequest,
+        user_id: Uuid
+    ) -> Result<PermissionOverwrite, AppError> {
+        let permissions = self.permissions
+            .require_guild_permissions(user_id, guild_id, Permissions::MANAGE_CHANNELS | Permissions::MANAGE_ROLES)
+            .await?;
+
+        if !permissions.contains(request.allow | request.deny) {
+            return Err(AppError::Forbidden("Cannot overwrite permissions you do not have".to_string()));
+        }
+
+        self.require_guild_channel(guild_id, channel_id).await?;
+
+        let target_exists = match request.target_type {
+            OverwriteType::Role => sqlx::query_scalar!(
+                r#"SELECT EXISTS(SELECT 1 FROM roles WHERE id = $1 AND guild_id = $2) as "exists!""#,
+                target_id, guild_id
+            )
+                .fetch_one(&self.db_pool)
+                .await,
+            OverwriteType::Member => sqlx::query_scalar!(
+                r#"SELECT EXISTS(SELECT 1 FROM guild_members WHERE user_id = $1 AND guild_id = $2) as "exists!""#,
+                target_id, guild_id
+            )
+                .fetch_one(&self.db_pool)
+                .await,
+        }
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if !target_exists {
+            return Err(AppError::NotFound("Overwrite target not found".to_string()));
+        }
+
+        let overwrite = sqlx::query_as!(PermissionOverwrite,
+            r#"
+                INSERT INTO channel_overwrites (channel_id, target_type, target_id, allow, deny)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (channel_id, target_id)
+                DO UPDATE SET target_type = EXCLUDED.target_type, allow = EXCLUDED.allow, deny = EXCLUDED.deny
+                RETURNING
+                    channel_id,
+                    target_type as "target_type: OverwriteType",
+                    target_id,
+                    allow as "allow: Permissions",
+                    deny as "deny: Permissions"
+            "#, channel_id, request.target_type as OverwriteType, target_id,
+            request.allow as Permissions, request.deny as Permissions
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to set overwrite: {}", e)))?;
+
+        self.sync_guild_subscriptions(guild_id).await?;
+
+        Ok(overwrite)
+    }
+
+    pub async fn delete_overwrite(
+        &self,
+        guild_id: Uuid,
+        channel_id: Uuid,
+        target_id: Uuid,
+        user_id: Uuid
+    ) -> Result<(), AppError> {
+        self.permissions
+            .require_guild_permissions(user_id, guild_id, Permissions::MANAGE_CHANNELS | Permissions::MANAGE_ROLES)
+            .await?;
+        self.require_guild_channel(guild_id, channel_id).await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM channel_overwrites WHERE channel_id = $1 AND target_id = $2",
+            channel_id, target_id
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete overwrite: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Overwrite not found".to_string()));
+        }
+
+        self.sync_guild_subscriptions(guild_id).await
+    }
+
+    async fn sync_guild_subscriptions(&self, guild_id: Uuid) -> Result<(), AppError> {
+        let members = sqlx::query_scalar!(
+            "SELECT user_id FROM guild_members WHERE guild_id = $1",
+            guild_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        sync_subscriptions(&self.db_pool, &self.permissions, &self.sessions, guild_id, &members).await
+    }
+}
+
+pub(crate) async fn guild_channel_ids(db_pool: &PgPool, guild_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+    sqlx::query_scalar!("SELECT id FROM channels WHERE guild_id = $1", guild_id)
+        .fetch_all(db_pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Database error: {}", e)))
+}
+
+/// The default channel is the first text channel of the guild, where system messages are posted.
+pub(crate) async fn default_channel_id(db_pool: &PgPool, guild_id: Uuid) -> Result<Option<Uuid>, AppError> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM channels
+            WHERE guild_id = $1 AND type = $2
+            ORDER BY position, created_at
+            LIMIT 1
+        "#, guild_id, ChannelType::Text as ChannelType
+    )
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|e| AppError::Database(format!("Database error: {}", e)))
+}
+
+/// Brings the live subscriptions of `user_ids` in line with the guild channels they can
+/// currently view, after a change to their roles or to the guild's permissions.
+pub(crate) async fn sync_subscriptions(
+    db_pool: &PgPool,
+    permissions: &PermissionsService,
+    sessions: &SessionRegistry<Uuid, WsMessage>,
+    guild_id: Uuid,
+    user_ids: &[Uuid]
+) -> Result<(), AppError> {
+    let channel_ids = guild_channel_ids(db_pool, guild_id).await?;
+    let mut visible_by_user = permissions.visible_guild_channels(guild_id, user_ids).await?;
+    let mut subscribable_by_user = permissions.subscribable_channels_by_user(&visible_by_user).await?;
+
+    for user_id in user_ids {
+        let visible = visible_by_user.remove(user_id).unwrap_or_default();
+        let subscribed = subscribable_by_user.remove(user_id).unwrap_or_default();
+        let visible_set: HashSet<&Uuid> = visible.iter().collect();
+        let hidden: Vec<Uuid> = channel_ids
+            .iter()
+            .filter(|channel_id| !visible_set.contains(channel_id))
+            .copied()
+            .collect();
+
+        sessions.unsubscribe_users(&[*user_id], &hidden).await;
+        sessions.subscribe_users(&[*user_id], &subscribed).await;
+    }
+
+    Ok(())
+}
\ No newline at end of file
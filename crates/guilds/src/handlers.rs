@@ -0,0 +1,327 @@
+use std::sync::Arc;
+use axum::{Extension, Json};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use uuid::Uuid;
+use blazing_auth::CurrentUser;
+use blazing_models::{
+    AppError, BanMemberRequest, CreateChannelRequest, CreateGuildRequest, CreateInviteRequest, CreateRoleRequest,
+    ReorderChannelsRequest, SetOverwriteRequest, UpdateChannelRequest, UpdateGuildRequest, UpdateRoleRequest,
+};
+use crate::{ChannelsService, GuildsService, InvitesService, MembersService, RolesService};
+
+pub async fn create_guild_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(guilds_service): State<Arc<GuildsService>>,
+    Json(request): Json<CreateGuildRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let guild = guilds_service.create_guild(request, current_user.user_id).await?;
+
+    Ok((StatusCode::CREATED, Json(guild)))
+}
+
+pub async fn list_guilds_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(guilds_service): State<Arc<GuildsService>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guilds = guilds_service.get_user_guilds(current_user.user_id).await?;
+
+    Ok(Json(guilds))
+}
+
+pub async fn get_guild_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(guilds_service): State<Arc<GuildsService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = guilds_service.get_guild(guild_id, current_user.user_id).await?;
+
+    Ok(Json(guild))
+}
+
+pub async fn update_guild_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(guilds_service): State<Arc<GuildsService>>,
+    Path(guild_id): Path<Uuid>,
+    Json(request): Json<UpdateGuildRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let guild = guilds_service.update_guild(guild_id, request, current_user.user_id).await?;
+
+    Ok(Json(guild))
+}
+
+pub async fn delete_guild_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(guilds_service): State<Arc<GuildsService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    guilds_service.delete_guild(guild_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_channel_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path(guild_id): Path<Uuid>,
+    Json(request): Json<CreateChannelRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let channel = channels_service.create_channel(guild_id, request, current_user.user_id).await?;
+
+    Ok((StatusCode::CREATED, Json(channel)))
+}
+
+pub async fn list_channels_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let channels = channels_service.get_channels(guild_id, current_user.user_id).await?;
+
+    Ok(Json(channels))
+}
+
+pub async fn reorder_channels_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path(guild_id): Path<Uuid>,
+    Json(request): Json<ReorderChannelsRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let channels = channels_service.reorder_channels(guild_id, request, current_user.user_id).await?;
+
+    Ok(Json(channels))
+}
+
+pub async fn update_channel_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path((guild_id, channel_id)): Path<(Uuid, Uuid)>,
+    Json(request): Json<UpdateChannelRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let channel = channels_service
+        .update_channel(guild_id, channel_id, request, current_user.user_id)
+        .await?;
+
+    Ok(Json(channel))
+}
+
+pub async fn delete_channel_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path((guild_id, channel_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    channels_service.delete_channel(guild_id, channel_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_overwrites_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path((guild_id, channel_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let overwrites = channels_service
+        .get_overwrites(guild_id, channel_id, current_user.user_id)
+        .await?;
+
+    Ok(Json(overwrites))
+}
+
+pub async fn set_overwrite_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path((guild_id, channel_id, target_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(request): Json<SetOverwriteRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let overwrite = channels_service
+        .set_overwrite(guild_id, channel_id, target_id, request, current_user.user_id)
+        .await?;
+
+    Ok(Json(overwrite))
+}
+
+pub async fn delete_overwrite_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(channels_service): State<Arc<ChannelsService>>,
+    Path((guild_id, channel_id, target_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    channels_service
+        .delete_overwrite(guild_id, channel_id, target_id, current_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_invite_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(invites_service): State<Arc<InvitesService>>,
+    Path(guild_id): Path<Uuid>,
+    Json(request): Json<CreateInviteRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let invite = invites_service.create_invite(guild_id, request, current_user.user_id).await?;
+
+    Ok((StatusCode::CREATED, Json(invite)))
+}
+
+pub async fn list_invites_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(invites_service): State<Arc<InvitesService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let invites = invites_service.get_guild_invites(guild_id, current_user.user_id).await?;
+
+    Ok(Json(invites))
+}
+
+pub async fn preview_invite_handler(
+    State(invites_service): State<Arc<InvitesService>>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let preview = invites_service.preview_invite(&code).await?;
+
+    Ok(Json(preview))
+}
+
+pub async fn accept_invite_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(invites_service): State<Arc<InvitesService>>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = invites_service.accept_invite(&code, current_user.user_id).await?;
+
+    Ok(Json(guild))
+}
+
+pub async fn revoke_invite_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(invites_service): State<Arc<InvitesService>>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    invites_service.revoke_invite(&code, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_members_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(members_service): State<Arc<MembersService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let members = members_service.get_members(guild_id, current_user.user_id).await?;
+
+    Ok(Json(members))
+}
+
+pub async fn leave_guild_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(members_service): State<Arc<MembersService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    members_service.leave_guild(guild_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn kick_member_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(members_service): State<Arc<MembersService>>,
+    Path((guild_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    members_service.kick_member(guild_id, user_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_bans_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(members_service): State<Arc<MembersService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let bans = members_service.get_bans(guild_id, current_user.user_id).await?;
+
+    Ok(Json(bans))
+}
+
+pub async fn ban_member_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(members_service): State<Arc<MembersService>>,
+    Path((guild_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(request): Json<BanMemberRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let ban = members_service.ban_member(guild_id, user_id, request, current_user.user_id).await?;
+
+    Ok(Json(ban))
+}
+
+pub async fn unban_member_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(members_service): State<Arc<MembersService>>,
+    Path((guild_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    members_service.unban_member(guild_id, user_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_roles_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(roles_service): State<Arc<RolesService>>,
+    Path(guild_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let roles = roles_service.get_roles(guild_id, current_user.user_id).await?;
+
+    Ok(Json(roles))
+}
+
+pub async fn create_role_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(roles_service): State<Arc<RolesService>>,
+    Path(guild_id): Path<Uuid>,
+    Json(request): Json<CreateRoleRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let role = roles_service.create_role(guild_id, request, current_user.user_id).await?;
+
+    Ok((StatusCode::CREATED, Json(role)))
+}
+
+pub async fn update_role_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(roles_service): State<Arc<RolesService>>,
+    Path((guild_id, role_id)): Path<(Uuid, Uuid)>,
+    Json(request): Json<UpdateRoleRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let role = roles_service.update_role(guild_id, role_id, request, current_user.user_id).await?;
+
+    Ok(Json(role))
+}
+
+pub async fn delete_role_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(roles_service): State<Arc<RolesService>>,
+    Path((guild_id, role_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    roles_service.delete_role(guild_id, role_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn add_member_role_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(roles_service): State<Arc<RolesService>>,
+    Path((guild_id, user_id, role_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    roles_service.add_member_role(guild_id, user_id, role_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_member_role_handler(
+    Extension(current_user): Extension<CurrentUser>,
+    State(roles_service): State<Arc<RolesService>>,
+    Path((guild_id, user_id, role_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    roles_service.remove_member_role(guild_id, user_id, role_id, current_user.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
\ No newline at end of file
@@ -0,0 +1,250 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use rand::distr::{Alphanumeric, SampleString};
+use sqlx::PgPool;
+use uuid::Uuid;
+use blazing_chat::{MessagesService, PermissionsService, WsMessage};
+use blazing_models::{AppError, CreateInviteRequest, Guild, Invite, InvitePreview, MessageType, Permissions};
+use blazing_ws::SessionRegistry;
+use crate::channels::default_channel_id;
+
+const INVITE_CODE_LENGTH: usize = 8;
+/// Attempts at finding an unused code before giving up.
+const INVITE_CODE_ATTEMPTS: usize = 5;
+const MAX_INVITE_AGE_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+pub struct InvitesService {
+    db_pool: PgPool,
+    messages_service: Arc<MessagesService>,
+    permissions: Arc<PermissionsService>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl InvitesService {
+    pub fn new(
+        db_pool: PgPool,
+        messages_service: Arc<MessagesService>,
+        permissions: Arc<PermissionsService>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, messages_service, permissions, sessions }
+    }
+
+    fn generate_code() -> String {
+        Alphanumeric.sample_string(&mut rand::rng(), INVITE_CODE_LENGTH)
+    }
+
+    pub async fn create_invite(
+        &self,
+        guild_id: Uuid,
+        request: CreateInviteRequest,
+        inviter_id: Uuid
+    ) -> Result<Invite, AppError> {
+        self.permissions
+            .require_guild_permissions(inviter_id, guild_id, Permissions::CREATE_INVITE)
+            .await?;
+
+        if let Some(max_uses) = request.max_uses
+            && max_uses < 1
+        {
+            return Err(AppError::BadRequest("max_uses must be at least 1".to_string()));
+        }
+
+        let expires_at = match request.max_age_seconds {
+            Some(seconds) if !(1..=MAX_INVITE_AGE_SECONDS).contains(&seconds) => {
+                return Err(AppError::BadRequest(format!(
+                    "max_age_seconds must be between 1 and {}", MAX_INVITE_AGE_SECONDS
+                )));
+            }
+            Some(seconds) => Some(Utc::now() + Duration::seconds(seconds)),
+            None => None,
+        };
+
+        for _ in 0..INVITE_CODE_ATTEMPTS {
+            let result = sqlx::query_as!(Invite,
+                r#"
+                    INSERT INTO guild_invites (code, guild_id, inviter_id, max_uses, expires_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    RETURNING code, guild_id, inviter_id, max_uses, uses, expires_at, created_at
+                "#, Self::generate_code(), guild_id, inviter_id, request.max_uses, expires_at
+            )
+                .fetch_one(&self.db_pool)
+                .await;
+
+            match result {
+                Ok(invite) => return Ok(invite),
+                // The generated code is already taken, try another one.
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => continue,
+                Err(e) => return Err(AppError::Database(format!("Failed to create invite: {}", e))),
+            }
+        }
+
+        Err(AppError::Internal("Failed to generate a unique invite code".to_string()))
+    }
+
+    pub async fn get_guild_invites(&self, guild_id: Uuid, user_id: Uuid) -> Result<Vec<Invite>, AppError> {
+        self.permissions
+            .require_guild_permissions(user_id, guild_id, Permissions::MANAGE_GUILD)
+            .await?;
+
+        let invites = sqlx::query_as!(Invite,
+            r#"
+                SELECT code, guild_id, inviter_id, max_uses, uses, expires_at, created_at
+                FROM guild_invites
+                WHERE guild_id = $1
+                ORDER BY created_at DESC
+            "#, guild_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(invites)
+    }
+
+    pub async fn preview_invite(&self, code: &str) -> Result<InvitePreview, AppError> {
+        sqlx::query_as!(InvitePreview,
+            r#"
+                SELECT
+                    i.code,
+                    g.id as guild_id,
+                    g.name as guild_name,
+                    g.icon_url as guild_icon_url,
+                    (SELECT COUNT(*) FROM guild_members gm WHERE gm.guild_id = g.id) as "member_count!",
+                    i.expires_at
+                FROM guild_invites i
+                INNER JOIN guilds g ON i.guild_id = g.id
+                WHERE i.code = $1
+                  AND (i.expires_at IS NULL OR i.expires_at > NOW())
+                  AND (i.max_uses IS NULL OR i.uses < i.max_uses)
+            "#, code
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Invite not found or expired".to_string()))
+    }
+
+    pub async fn accept_invite(&self, code: &str, user_id: Uuid) -> Result<Guild, AppError> {
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let invite = sqlx::query_as!(Invite,
+            r#"
+                SELECT code, guild_id, inviter_id, max_uses, uses, expires_at, created_at
+                FROM guild_invites
+                WHERE code = $1
+                  AND (expires_at IS NULL OR expires_at > NOW())
+                  AND (max_uses IS NULL OR uses < max_uses)
+                FOR UPDATE
+            "#, code
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Invite not found or expired".to_string()))?;
+
+        let is_banned = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM guild_bans WHERE guild_id = $1 AND user_id = $2
+                ) as "exists!"
+            "#, invite.guild_id, user_id
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if is_banned {
+            return Err(AppError::Forbidden("You are banned from this guild".to_string()));
+        }
+
+        let joined = sqlx::query!(
+            r#"
+                INSERT INTO guild_members (guild_id, user_id)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+            "#, invite.guild_id, user_id
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to join guild: {}", e)))?
+            .rows_affected() > 0;
+
+        if joined {
+            sqlx::query!(
+                "UPDATE guild_invites SET uses = uses + 1 WHERE code = $1",
+                invite.code
+            )
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+        }
+
+        let guild = sqlx::query_as!(Guild,
+            "SELECT id, name, owner_id, icon_url, created_at FROM guilds WHERE id = $1",
+            invite.guild_id
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if joined {
+            tracing::info!("User {} joined guild {} via invite {}", user_id, guild.id, invite.code);
+            // The join is committed; a failure here should not be reported as a failed join.
+            if let Err(e) = self.on_member_joined(guild.id, user_id).await {
+                tracing::warn!("Failed to finish joining user {} to guild {}: {}", user_id, guild.id, e);
+            }
+        }
+
+        Ok(guild)
+    }
+
+    async fn on_member_joined(&self, guild_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let channel_ids = self.permissions
+            .visible_guild_channels(guild_id, &[user_id])
+            .await?
+            .remove(&user_id)
+            .unwrap_or_default();
+        let channel_ids = self.permissions.subscribable_channels(user_id, &channel_ids).await?;
+        self.sessions.subscribe_users(&[user_id], &channel_ids).await;
+
+        if let Some(channel_id) = default_channel_id(&self.db_pool, guild_id).await? {
+            self.messages_service
+                .create_system_message(channel_id, user_id, MessageType::UserJoin)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn revoke_invite(&self, code: &str, user_id: Uuid) -> Result<(), AppError> {
+        let invite = sqlx::query!(
+            "SELECT guild_id, inviter_id FROM guild_invites WHERE code = $1",
+            code
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Invite not found".to_string()))?;
+
+        if invite.inviter_id != user_id {
+            self.permissions
+                .require_guild_permissions(user_id, invite.guild_id, Permissions::MANAGE_GUILD)
+                .await?;
+        }
+
+        sqlx::query!("DELETE FROM guild_invites WHERE code = $1", code)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to revoke invite: {}", e)))?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -1,3 +1,19 @@
+mod service;
+mod channels;
+mod invites;
+mod members;
+mod roles;
+mod handlers;
+mod routes;
+
+pub use service::*;
+pub use channels::*;
+pub use invites::*;
+pub use members::*;
+pub use roles::*;
+pub use handlers::*;
+pub use routes::*;
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
@@ -11,4 +27,4 @@ mod tests {
         let result = add(2, 2);
         assert_eq!(result, 4);
     }
-}
+}
\ No newline at end of file
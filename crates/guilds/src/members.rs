@@ -0,0 +1,256 @@
+use std::sync::Arc;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use blazing_chat::{MessagesService, PermissionsService, WsMessage};
+use blazing_models::{AppError, BanMemberRequest, GuildBan, GuildMember, MessageType, Permissions};
+use blazing_ws::SessionRegistry;
+use crate::channels::{default_channel_id, guild_channel_ids};
+
+const MAX_BAN_REASON_LENGTH: usize = 512;
+
+pub struct MembersService {
+    db_pool: PgPool,
+    messages_service: Arc<MessagesService>,
+    permissions: Arc<PermissionsService>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl MembersService {
+    pub fn new(
+        db_pool: PgPool,
+        messages_service: Arc<MessagesService>,
+        permissions: Arc<PermissionsService>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, messages_service, permissions, sessions }
+    }
+
+    async fn get_guild_owner(&self, guild_id: Uuid) -> Result<Uuid, AppError> {
+        sqlx::query_scalar!("SELECT owner_id FROM guilds WHERE id = $1", guild_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Guild not found".to_string()))
+    }
+
+    /// Checks that `actor_id` holds `required`, outranks `target_id`, and that `target_id` is
+    /// neither the owner nor the actor.
+    async fn require_moderator(
+        &self,
+        guild_id: Uuid,
+        actor_id: Uuid,
+        target_id: Uuid,
+        required: Permissions
+    ) -> Result<(), AppError> {
+        self.permissions
+            .require_guild_permissions(actor_id, guild_id, required)
+            .await?;
+
+        if target_id == actor_id {
+            return Err(AppError::BadRequest("You cannot moderate yourself".to_string()));
+        }
+        if target_id == self.get_guild_owner(guild_id).await? {
+            return Err(AppError::BadRequest("The guild owner cannot be removed".to_string()));
+        }
+
+        self.permissions.require_outranks(actor_id, target_id, guild_id).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_members(&self, guild_id: Uuid, user_id: Uuid) -> Result<Vec<GuildMember>, AppError> {
+        self.permissions.require_member(user_id, guild_id).await?;
+
+        let members = sqlx::query_as!(GuildMember,
+            r#"
+                SELECT
+                    u.id as user_id,
+                    u.username,
+                    u.avatar_url,
+                    gm.joined_at,
+                    COALESCE(
+                        ARRAY_AGG(mr.role_id) FILTER (WHERE mr.role_id IS NOT NULL),
+                        '{}'
+                    ) as "roles!"
+                FROM guild_members gm
+                INNER JOIN users u ON gm.user_id = u.id
+                LEFT JOIN member_roles mr ON mr.guild_id = gm.guild_id AND mr.user_id = gm.user_id
+                WHERE gm.guild_id = $1
+                GROUP BY u.id, gm.joined_at
+                ORDER BY gm.joined_at
+            "#, guild_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(members)
+    }
+
+    pub async fn leave_guild(&self, guild_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.permissions.require_member(user_id, guild_id).await?;
+
+        if self.get_guild_owner(guild_id).await? == user_id {
+            return Err(AppError::BadRequest("The guild owner cannot leave, delete the guild instead".to_string()));
+        }
+
+        self.remove_member(guild_id, user_id).await
+    }
+
+    pub async fn kick_member(&self, guild_id: Uuid, target_id: Uuid, actor_id: Uuid) -> Result<(), AppError> {
+        self.require_moderator(guild_id, actor_id, target_id, Permissions::KICK_MEMBERS).await?;
+
+        self.remove_member(guild_id, target_id).await?;
+        tracing::info!("User {} kicked {} from guild {}", actor_id, target_id, guild_id);
+
+        Ok(())
+    }
+
+    pub async fn ban_member(
+        &self,
+        guild_id: Uuid,
+        target_id: Uuid,
+        request: BanMemberRequest,
+        actor_id: Uuid
+    ) -> Result<GuildBan, AppError> {
+        self.require_moderator(guild_id, actor_id, target_id, Permissions::BAN_MEMBERS).await?;
+
+        if let Some(reason) = &request.reason
+            && reason.chars().count() > MAX_BAN_REASON_LENGTH
+        {
+            return Err(AppError::BadRequest("Ban reason too long".to_string()));
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let ban = sqlx::query_as!(GuildBan,
+            r#"
+                INSERT INTO guild_bans (guild_id, user_id, banned_by, reason)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (guild_id, user_id)
+                DO UPDATE SET banned_by = EXCLUDED.banned_by, reason = EXCLUDED.reason
+                RETURNING guild_id, user_id, banned_by, reason, created_at
+            "#, guild_id, target_id, actor_id, request.reason
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to ban member: {}", e)))?;
+
+        let was_member = Self::delete_member(&mut tx, guild_id, target_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        // The ban is committed; a failure here should not be reported as a failed ban.
+        if was_member && let Err(e) = self.on_member_removed(guild_id, target_id).await {
+            tracing::warn!("Failed to finish removing user {} from guild {}: {}", target_id, guild_id, e);
+        }
+        tracing::info!("User {} banned {} from guild {}", actor_id, target_id, guild_id);
+
+        Ok(ban)
+    }
+
+    pub async fn unban_member(&self, guild_id: Uuid, target_id: Uuid, actor_id: Uuid) -> Result<(), AppError> {
+        self.require_moderator(guild_id, actor_id, target_id, Permissions::BAN_MEMBERS).await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM guild_bans WHERE guild_id = $1 AND user_id = $2",
+            guild_id, target_id
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to unban member: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Ban not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_bans(&self, guild_id: Uuid, user_id: Uuid) -> Result<Vec<GuildBan>, AppError> {
+        self.permissions
+            .require_guild_permissions(user_id, guild_id, Permissions::BAN_MEMBERS)
+            .await?;
+
+        let bans = sqlx::query_as!(GuildBan,
+            r#"
+                SELECT guild_id, user_id, banned_by, reason, created_at
+                FROM guild_bans
+                WHERE guild_id = $1
+                ORDER BY created_at DESC
+            "#, guild_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(bans)
+    }
+
+    /// Deletes the membership of `user_id` together with their channel overwrites in the
+    /// guild. Returns whether they were a member.
+    async fn delete_member(conn: &mut PgConnection, guild_id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM guild_members WHERE guild_id = $1 AND user_id = $2",
+            guild_id, user_id
+        )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to remove member: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM channel_overwrites
+                WHERE target_type = 'member' AND target_id = $2
+                  AND channel_id IN (SELECT id FROM channels WHERE guild_id = $1)
+            "#, guild_id, user_id
+        )
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to remove member: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes the membership, drops the user's live subscriptions to the guild's
+    /// channels and posts a `UserLeave` system message.
+    async fn remove_member(&self, guild_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        if !Self::delete_member(&mut tx, guild_id, user_id).await? {
+            return Err(AppError::NotFound("Member not found".to_string()));
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        // The removal is committed; a failure here should not be reported as a failed removal.
+        if let Err(e) = self.on_member_removed(guild_id, user_id).await {
+            tracing::warn!("Failed to finish removing user {} from guild {}: {}", user_id, guild_id, e);
+        }
+
+        Ok(())
+    }
+
+    async fn on_member_removed(&self, guild_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let channel_ids = guild_channel_ids(&self.db_pool, guild_id).await?;
+        self.sessions.unsubscribe_users(&[user_id], &channel_ids).await;
+
+        if let Some(channel_id) = default_channel_id(&self.db_pool, guild_id).await? {
+            self.messages_service
+                .create_system_message(channel_id, user_id, MessageType::UserLeave)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,320 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use blazing_chat::{PermissionsService, WsMessage};
+use blazing_models::{AppError, CreateRoleRequest, Permissions, Role, UpdateRoleRequest};
+use blazing_ws::SessionRegistry;
+use crate::channels::sync_subscriptions;
+
+const MAX_ROLE_NAME_LENGTH: usize = 100;
+/// Position given to new roles: directly above @everyone, below every existing role.
+const NEW_ROLE_POSITION: i32 = 1;
+
+pub struct RolesService {
+    db_pool: PgPool,
+    permissions: Arc<PermissionsService>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl RolesService {
+    pub fn new(
+        db_pool: PgPool,
+        permissions: Arc<PermissionsService>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, permissions, sessions }
+    }
+
+    fn validate_name(name: &str) -> Result<String, AppError> {
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(AppError::BadRequest("Role name cannot be empty".to_string()));
+        }
+        if name.chars().count() > MAX_ROLE_NAME_LENGTH {
+            return Err(AppError::BadRequest("Role name too long".to_string()));
+        }
+
+        Ok(name.to_string())
+    }
+
+    /// Requires MANAGE_ROLES and that `granted` does not exceed the actor's own permissions,
+    /// so that roles cannot be used to escalate privileges.
+    async fn require_manage_roles(
+        &self,
+        guild_id: Uuid,
+        user_id: Uuid,
+        granted: Permissions
+    ) -> Result<(), AppError> {
+        let permissions = self.permissions
+            .require_guild_permissions(user_id, guild_id, Permissions::MANAGE_ROLES)
+            .await?;
+
+        if !permissions.contains(granted) {
+            return Err(AppError::Forbidden("Cannot grant permissions you do not have".to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn get_role(&self, guild_id: Uuid, role_id: Uuid) -> Result<Role, AppError> {
+        sqlx::query_as!(Role,
+            r#"
+                SELECT id, guild_id, name, permissions as "permissions: Permissions", position, is_default, created_at
+                FROM roles
+                WHERE id = $1 AND guild_id = $2
+            "#, role_id, guild_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Role not found".to_string()))
+    }
+
+    /// Members whose permissions depend on `role`.
+    async fn role_members(&self, role: &Role) -> Result<Vec<Uuid>, AppError> {
+        sqlx::query_scalar!(
+            r#"
+                SELECT gm.user_id
+                FROM guild_members gm
+                WHERE gm.guild_id = $1 AND ($2 OR EXISTS(
+                    SELECT 1 FROM member_roles mr WHERE mr.role_id = $3 AND mr.user_id = gm.user_id
+                ))
+            "#, role.guild_id, role.is_default, role.id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))
+    }
+
+    pub async fn get_roles(&self, guild_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, AppError> {
+        self.permissions.require_member(user_id, guild_id).await?;
+
+        let roles = sqlx::query_as!(Role,
+            r#"
+                SELECT id, guild_id, name, permissions as "permissions: Permissions", position, is_default, created_at
+                FROM roles
+                WHERE guild_id = $1
+                ORDER BY position, created_at
+            "#, guild_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(roles)
+    }
+
+    pub async fn create_role(
+        &self,
+        guild_id: Uuid,
+        request: CreateRoleRequest,
+        user_id: Uuid
+    ) -> Result<Role, AppError> {
+        let permissions = request.permissions.unwrap_or(Permissions::empty());
+        self.require_manage_roles(guild_id, user_id, permissions).await?;
+
+        let name = Self::validate_name(&request.name)?;
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        // Serializes role creation per guild so concurrent inserts don't share a position.
+        sqlx::query!("SELECT id FROM guilds WHERE id = $1 FOR UPDATE", guild_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create role: {}", e)))?;
+
+        // New roles go to the bottom of the hierarchy so the creator still outranks them.
+        sqlx::query!(
+            "UPDATE roles SET position = position + 1 WHERE guild_id = $1 AND position >= $2",
+            guild_id, NEW_ROLE_POSITION
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create role: {}", e)))?;
+
+        let role = sqlx::query_as!(Role,
+            r#"
+                INSERT INTO roles (guild_id, name, permissions, position)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, guild_id, name, permissions as "permissions: Permissions", position, is_default, created_at
+            "#, guild_id, name, permissions as Permissions, NEW_ROLE_POSITION
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create role: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(role)
+    }
+
+    pub async fn update_role(
+        &self,
+        guild_id: Uuid,
+        role_id: Uuid,
+        request: UpdateRoleRequest,
+        user_id: Uuid
+    ) -> Result<Role, AppError> {
+        self.require_manage_roles(guild_id, user_id, request.permissions.unwrap_or(Permissions::empty())).await?;
+
+        let role = self.get_role(guild_id, role_id).await?;
+        self.permissions.require_above(user_id, guild_id, role.position).await?;
+        if let Some(position) = request.position {
+            self.permissions.require_above(user_id, guild_id, position).await?;
+        }
+
+        if role.is_default && request.name.is_some() {
+            return Err(AppError::BadRequest("The default role cannot be renamed".to_string()));
+        }
+
+        let name = request.name
+            .as_deref()
+            .map(Self::validate_name)
+            .transpose()?;
+
+        let updated = sqlx::query_as!(Role,
+            r#"
+                UPDATE roles
+                SET name = COALESCE($2, name),
+                    permissions = COALESCE($3, permissions),
+                    position = COALESCE($4, position)
+                WHERE id = $1
+                RETURNING id, guild_id, name, permissions as "permissions: Permissions", position, is_default, created_at
+            "#, role_id, name, request.permissions as Option<Permissions>, request.position
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update role: {}", e)))?;
+
+        if updated.permissions != role.permissions {
+            let members = self.role_members(&updated).await?;
+            sync_subscriptions(&self.db_pool, &self.permissions, &self.sessions, guild_id, &members).await?;
+        }
+
+        Ok(updated)
+    }
+
+    pub async fn delete_role(&self, guild_id: Uuid, role_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.require_manage_roles(guild_id, user_id, Permissions::empty()).await?;
+
+        let role = self.get_role(guild_id, role_id).await?;
+
+        if role.is_default {
+            return Err(AppError::BadRequest("The default role cannot be deleted".to_string()));
+        }
+
+        self.permissions.require_above(user_id, guild_id, role.position).await?;
+
+        let members = self.role_members(&role).await?;
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        sqlx::query!("DELETE FROM channel_overwrites WHERE target_id = $1", role_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete role: {}", e)))?;
+
+        sqlx::query!("DELETE FROM roles WHERE id = $1", role_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete role: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        sync_subscriptions(&self.db_pool, &self.permissions, &self.sessions, guild_id, &members).await
+    }
+
+    pub async fn add_member_role(
+        &self,
+        guild_id: Uuid,
+        target_id: Uuid,
+        role_id: Uuid,
+        user_id: Uuid
+    ) -> Result<(), AppError> {
+        let role = self.get_role(guild_id, role_id).await?;
+        self.require_manage_roles(guild_id, user_id, role.permissions).await?;
+        self.permissions.require_above(user_id, guild_id, role.position).await?;
+
+        if role.is_default {
+            return Err(AppError::BadRequest("The default role is implicitly assigned".to_string()));
+        }
+
+        self.permissions.require_member(target_id, guild_id)
+            .await
+            .map_err(|_| AppError::NotFound("Member not found".to_string()))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO member_roles (guild_id, user_id, role_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+            "#, guild_id, target_id, role_id
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to assign role: {}", e)))?;
+
+        sync_subscriptions(&self.db_pool, &self.permissions, &self.sessions, guild_id, &[target_id]).await
+    }
+
+    pub async fn remove_member_role(
+        &self,
+        guild_id: Uuid,
+        target_id: Uuid,
+        role_id: Uuid,
+        user_id: Uuid
+    ) -> Result<(), AppError> {
+        let role = self.get_role(guild_id, role_id).await?;
+        self.require_manage_roles(guild_id, user_id, role.permissions).await?;
+        self.permissions.require_above(user_id, guild_id, role.position).await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM member_roles WHERE guild_id = $1 AND user_id = $2 AND role_id = $3",
+            guild_id, target_id, role_id
+        )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to remove role: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Member does not have this role".to_string()));
+        }
+
+        sync_subscriptions(&self.db_pool, &self.permissions, &self.sessions, guild_id, &[target_id]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manager_outranks_roles_they_create() {
+        // A non-owner manager's highest role is at least the lowest non-default one,
+        // which creating a role shifts up past the new role.
+        let highest_after_create = NEW_ROLE_POSITION + 1;
+
+        assert!(PermissionsService::check_above(Some(highest_after_create), NEW_ROLE_POSITION).is_ok());
+    }
+
+    #[test]
+    fn everyone_managers_do_not_outrank_new_roles() {
+        assert!(PermissionsService::check_above(Some(0), NEW_ROLE_POSITION).is_err());
+    }
+
+    #[test]
+    fn owner_outranks_new_roles() {
+        assert!(PermissionsService::check_above(None, NEW_ROLE_POSITION).is_ok());
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+use axum::{middleware, Router};
+use axum::routing::{delete, get, patch, post, put};
+use blazing_auth::{auth_middleware, AuthService};
+use crate::{handlers, ChannelsService, GuildsService, InvitesService, MembersService, RolesService};
+
+pub fn create_guild_routes(
+    guilds_service: Arc<GuildsService>,
+    channels_service: Arc<ChannelsService>,
+    invites_service: Arc<InvitesService>,
+    members_service: Arc<MembersService>,
+    roles_service: Arc<RolesService>,
+    auth_service: Arc<AuthService>,
+) -> Router {
+    let guild_routes = Router::new()
+        .route("/", get(handlers::list_guilds_handler).post(handlers::create_guild_handler))
+        .route(
+            "/{guild_id}",
+            get(handlers::get_guild_handler)
+                .patch(handlers::update_guild_handler)
+                .delete(handlers::delete_guild_handler),
+        )
+        .with_state(guilds_service);
+
+    let channel_routes = Router::new()
+        .route(
+            "/{guild_id}/channels",
+            get(handlers::list_channels_handler)
+                .post(handlers::create_channel_handler)
+                .patch(handlers::reorder_channels_handler),
+        )
+        .route(
+            "/{guild_id}/channels/{channel_id}",
+            patch(handlers::update_channel_handler).delete(handlers::delete_channel_handler),
+        )
+        .route(
+            "/{guild_id}/channels/{channel_id}/permissions",
+            get(handlers::list_overwrites_handler),
+        )
+        .route(
+            "/{guild_id}/channels/{channel_id}/permissions/{target_id}",
+            put(handlers::set_overwrite_handler).delete(handlers::delete_overwrite_handler),
+        )
+        .with_state(channels_service);
+
+    let invite_routes = Router::new()
+        .route(
+            "/{guild_id}/invites",
+            get(handlers::list_invites_handler).post(handlers::create_invite_handler),
+        )
+        .with_state(invites_service);
+
+    let member_routes = Router::new()
+        .route("/{guild_id}/members", get(handlers::list_members_handler))
+        .route("/{guild_id}/members/{user_id}", delete(handlers::kick_member_handler))
+        .route("/{guild_id}/leave", post(handlers::leave_guild_handler))
+        .route("/{guild_id}/bans", get(handlers::list_bans_handler))
+        .route(
+            "/{guild_id}/bans/{user_id}",
+            put(handlers::ban_member_handler).delete(handlers::unban_member_handler),
+        )
+        .with_state(members_service);
+
+    let role_routes = Router::new()
+        .route(
+            "/{guild_id}/roles",
+            get(handlers::list_roles_handler).post(handlers::create_role_handler),
+        )
+        .route(
+            "/{guild_id}/roles/{role_id}",
+            patch(handlers::update_role_handler).delete(handlers::delete_role_handler),
+        )
+        .route(
+            "/{guild_id}/members/{user_id}/roles/{role_id}",
+            put(handlers::add_member_role_handler).delete(handlers::remove_member_role_handler),
+        )
+        .with_state(roles_service);
+
+    guild_routes
+        .merge(channel_routes)
+        .merge(invite_routes)
+        .merge(member_routes)
+        .merge(role_routes)
+        .layer(middleware::from_fn_with_state(
+            auth_service,
+            auth_middleware,
+        ))
+}
+
+pub fn create_invite_routes(
+    invites_service: Arc<InvitesService>,
+    auth_service: Arc<AuthService>,
+) -> Router {
+    let auth_layer = middleware::from_fn_with_state(
+        auth_service,
+        auth_middleware
+    );
+
+    Router::new()
+        .route(
+            "/{code}",
+            get(handlers::preview_invite_handler).merge(
+                post(handlers::accept_invite_handler)
+                    .delete(handlers::revoke_invite_handler)
+                    .layer(auth_layer),
+            ),
+        )
+        .with_state(invites_service)
+}
\ No newline at end of file
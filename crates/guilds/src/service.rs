@@ -0,0 +1,201 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use blazing_chat::{PermissionsService, WsMessage};
+use blazing_models::{AppError, ChannelType, CreateGuildRequest, Guild, Permissions, UpdateGuildRequest};
+use blazing_ws::SessionRegistry;
+
+const MAX_GUILD_NAME_LENGTH: usize = 100;
+const DEFAULT_CHANNEL_NAME: &str = "general";
+const DEFAULT_ROLE_NAME: &str = "@everyone";
+
+pub struct GuildsService {
+    db_pool: PgPool,
+    permissions: Arc<PermissionsService>,
+    sessions: Arc<SessionRegistry<Uuid, WsMessage>>,
+}
+
+impl GuildsService {
+    pub fn new(
+        db_pool: PgPool,
+        permissions: Arc<PermissionsService>,
+        sessions: Arc<SessionRegistry<Uuid, WsMessage>>
+    ) -> Self {
+        Self { db_pool, permissions, sessions }
+    }
+
+    pub fn get_pool(&self) -> &PgPool {
+        &self.db_pool
+    }
+
+    fn validate_name(name: &str) -> Result<String, AppError> {
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(AppError::BadRequest("Guild name cannot be empty".to_string()));
+        }
+        if name.chars().count() > MAX_GUILD_NAME_LENGTH {
+            return Err(AppError::BadRequest("Guild name too long".to_string()));
+        }
+
+        Ok(name.to_string())
+    }
+
+    pub async fn create_guild(&self, request: CreateGuildRequest, owner_id: Uuid) -> Result<Guild, AppError> {
+        let name = Self::validate_name(&request.name)?;
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let guild = sqlx::query_as!(Guild,
+            r#"
+                INSERT INTO guilds (name, owner_id, icon_url)
+                VALUES ($1, $2, $3)
+                RETURNING id, name, owner_id, icon_url, created_at
+            "#, name, owner_id, request.icon_url
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create guild: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO guild_members (guild_id, user_id)
+                VALUES ($1, $2)
+            "#, guild.id, owner_id
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to add guild owner: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO channels (guild_id, name, type, position)
+                VALUES ($1, $2, $3, 0)
+            "#, guild.id, DEFAULT_CHANNEL_NAME, ChannelType::Text as ChannelType
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create default channel: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO roles (guild_id, name, permissions, is_default)
+                VALUES ($1, $2, $3, TRUE)
+            "#, guild.id, DEFAULT_ROLE_NAME, Permissions::DEFAULT as Permissions
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to create default role: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        tracing::info!("User {} created guild {}", owner_id, guild.id);
+
+        Ok(guild)
+    }
+
+    pub async fn get_user_guilds(&self, user_id: Uuid) -> Result<Vec<Guild>, AppError> {
+        let guilds = sqlx::query_as!(Guild,
+            r#"
+                SELECT g.id, g.name, g.owner_id, g.icon_url, g.created_at
+                FROM guilds g
+                INNER JOIN guild_members gm ON g.id = gm.guild_id
+                WHERE gm.user_id = $1
+                ORDER BY gm.joined_at
+            "#, user_id
+        )
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        Ok(guilds)
+    }
+
+    pub async fn get_guild(&self, guild_id: Uuid, user_id: Uuid) -> Result<Guild, AppError> {
+        sqlx::query_as!(Guild,
+            r#"
+                SELECT g.id, g.name, g.owner_id, g.icon_url, g.created_at
+                FROM guilds g
+                INNER JOIN guild_members gm ON g.id = gm.guild_id
+                WHERE g.id = $1 AND gm.user_id = $2
+            "#, guild_id, user_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?
+            .ok_or(AppError::NotFound("Guild not found".to_string()))
+    }
+
+    pub async fn update_guild(
+        &self,
+        guild_id: Uuid,
+        request: UpdateGuildRequest,
+        user_id: Uuid
+    ) -> Result<Guild, AppError> {
+        self.permissions
+            .require_guild_permissions(user_id, guild_id, Permissions::MANAGE_GUILD)
+            .await?;
+
+        let name = request.name
+            .as_deref()
+            .map(Self::validate_name)
+            .transpose()?;
+
+        let guild = sqlx::query_as!(Guild,
+            r#"
+                UPDATE guilds
+                SET name = COALESCE($2, name),
+                    icon_url = COALESCE($3, icon_url)
+                WHERE id = $1
+                RETURNING id, name, owner_id, icon_url, created_at
+            "#, guild_id, name, request.icon_url
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to update guild: {}", e)))?;
+
+        Ok(guild)
+    }
+
+    pub async fn delete_guild(&self, guild_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let guild = self.get_guild(guild_id, user_id).await?;
+
+        if guild.owner_id != user_id {
+            return Err(AppError::Forbidden("Only the guild owner can delete the guild".to_string()));
+        }
+
+        let mut tx = self.db_pool
+            .begin()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let members = sqlx::query_scalar!("SELECT user_id FROM guild_members WHERE guild_id = $1", guild_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        let channel_ids = sqlx::query_scalar!("SELECT id FROM channels WHERE guild_id = $1", guild_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        sqlx::query!("DELETE FROM guilds WHERE id = $1", guild_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database(format!("Failed to delete guild: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::Database(format!("Database error: {}", e)))?;
+
+        self.sessions.unsubscribe_users(&members, &channel_ids).await;
+        tracing::info!("User {} deleted guild {}", user_id, guild_id);
+
+        Ok(())
+    }
+}
\ No newline at end of file
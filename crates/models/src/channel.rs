@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use sqlx::Type;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelType {
+    Text,
+    Voice,
+    Announcement,
+    Dm,
+    GroupDm,
+    Thread,
+}
+
+impl ChannelType {
+    pub fn is_dm(self) -> bool {
+        matches!(self, ChannelType::Dm | ChannelType::GroupDm)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Channel {
+    pub id: Uuid,
+    pub guild_id: Uuid,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub channel_type: ChannelType,
+    pub position: i32,
+    /// Seconds a member has to wait between two messages, 0 when slow mode is off.
+    pub slowmode_seconds: i32,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateChannelRequest {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub channel_type: Option<ChannelType>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateChannelRequest {
+    pub name: Option<String>,
+    pub slowmode_seconds: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChannelPosition {
+    pub id: Uuid,
+    pub position: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderChannelsRequest {
+    pub channels: Vec<ChannelPosition>,
+}
\ No newline at end of file
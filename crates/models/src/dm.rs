@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::ChannelType;
+
+/// A direct message channel: `dm` with exactly two recipients, or a named `group_dm`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmChannel {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub channel_type: ChannelType,
+    pub name: Option<String>,
+    pub recipients: Vec<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Opens a DM with one recipient, or a group DM with several. The caller is always
+/// added as a recipient.
+#[derive(Debug, Deserialize)]
+pub struct CreateDmRequest {
+    pub recipient_ids: Vec<Uuid>,
+    pub name: Option<String>,
+}
\ No newline at end of file
@@ -1,10 +1,11 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use std::fmt;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub enum AppError {
@@ -14,6 +15,11 @@ pub enum AppError {
     NotFound(String),
     Internal(String),
     Forbidden(String),
+    /// The request was refused by a rate limit and may be retried after `retry_after`.
+    RateLimited {
+        message: String,
+        retry_after: Duration,
+    },
 }
 
 impl fmt::Display for AppError {
@@ -25,6 +31,9 @@ impl fmt::Display for AppError {
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::Internal(msg) => write!(f, "Internal error: {}", msg),
             AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AppError::RateLimited { message, retry_after } => {
+                write!(f, "Rate limited: {} (retry after {:.3}s)", message, retry_after.as_secs_f64())
+            }
         }
     }
 }
@@ -40,6 +49,19 @@ impl IntoResponse for AppError {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::RateLimited { message, retry_after } => {
+                let body = Json(json!({
+                    "error": message,
+                    "retry_after": retry_after.as_secs_f64()
+                }));
+                let retry_after_header = retry_after.as_secs_f64().ceil().to_string();
+
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after_header)],
+                    body,
+                ).into_response();
+            }
         };
 
         let body = Json(json!({
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Guild {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub icon_url: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGuildRequest {
+    pub name: String,
+    pub icon_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGuildRequest {
+    pub name: Option<String>,
+    pub icon_url: Option<String>,
+}
\ No newline at end of file
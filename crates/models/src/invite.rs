@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invite {
+    pub code: String,
+    pub guild_id: Uuid,
+    pub inviter_id: Uuid,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteRequest {
+    pub max_age_seconds: Option<i64>,
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InvitePreview {
+    pub code: String,
+    pub guild_id: Uuid,
+    pub guild_name: String,
+    pub guild_icon_url: Option<String>,
+    pub member_count: i64,
+    pub expires_at: Option<DateTime<Utc>>,
+}
\ No newline at end of file
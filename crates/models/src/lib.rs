@@ -1,7 +1,27 @@
 mod user;
 mod error;
 mod message;
+mod guild;
+mod channel;
+mod invite;
+mod member;
+mod permission;
+mod role;
+mod dm;
+mod relationship;
+mod thread;
+mod scheduled;
 
 pub use user::*;
 pub use error::*;
-pub use message::*;
\ No newline at end of file
+pub use message::*;
+pub use guild::*;
+pub use channel::*;
+pub use invite::*;
+pub use member::*;
+pub use permission::*;
+pub use role::*;
+pub use dm::*;
+pub use relationship::*;
+pub use thread::*;
+pub use scheduled::*;
\ No newline at end of file
@@ -32,17 +32,120 @@ pub struct Message {
     pub attachments: Option<Json<Vec<Attachment>>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub reply_to: Option<Uuid>,
+    pub pinned_at: Option<DateTime<Utc>>,
+    pub referenced_message: Option<Json<MessagePreview>>,
+    pub reactions: Json<Vec<ReactionCount>>,
+    pub mentions: Vec<Uuid>,
+    pub mention_roles: Vec<Uuid>,
+    pub mention_everyone: bool,
+    /// The thread started from this message, if any.
+    pub thread_id: Option<Uuid>,
+}
+
+/// Number of users who reacted with `emoji`; `me` is set if the viewer is one of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    pub me: bool,
+}
+
+/// Compact view of the message a reply refers to. `content` is truncated and left
+/// empty once the message has been deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePreview {
+    pub id: Uuid,
+    pub author_id: Uuid,
+    pub content: String,
+    pub deleted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendMessageRequest {
+    /// Id to create the message with, used by server-side deliveries that may be retried.
+    /// Never taken from clients.
+    #[serde(skip)]
+    pub id: Option<Uuid>,
     pub channel_id: Uuid,
     pub content: String,
     pub message_type: Option<MessageType>,
     pub attachments: Option<Json<Vec<Attachment>>>,
+    pub reply_to: Option<Uuid>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateMessageRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkDeleteMessagesRequest {
+    pub message_ids: Vec<Uuid>,
+}
+
+/// A prior revision of an edited message. `edited_at` is when it was replaced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageEdit {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub content: String,
+    pub edited_at: DateTime<Utc>,
+}
+
+/// A page of message history. At most one of `before`, `after` and `around` may be set;
+/// without a cursor the latest messages are returned.
 #[derive(Serialize, Deserialize)]
-pub struct GetMessagesRequest {
+pub struct MessageHistoryRequest {
+    pub channel_id: Uuid,
+    pub before: Option<Uuid>,
+    pub after: Option<Uuid>,
+    pub around: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+/// Messages are ordered newest first. `next_cursor` continues in the direction of the
+/// query (`after` for `after` pages, `before` otherwise) and is `None` on the last page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePage {
+    pub messages: Vec<Message>,
+    pub next_cursor: Option<Uuid>,
+}
+
+/// Full-text search over the messages the caller can read. `query` uses web search
+/// syntax (quoted phrases, `or`, `-exclusion`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMessagesRequest {
+    pub query: String,
+    pub guild_id: Option<Uuid>,
+    pub channel_id: Option<Uuid>,
+    pub author_id: Option<Uuid>,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+    pub has_attachment: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// A matching message with the matched terms wrapped in `**` in `highlight`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub message: Message,
+    pub highlight: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub total: i64,
+    pub results: Vec<SearchResult>,
+}
+
+/// Unread state of one channel for the caller. Messages sent by the caller never count
+/// as unread.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelReadState {
     pub channel_id: Uuid,
+    pub last_read_message_id: Option<Uuid>,
+    pub unread_count: i64,
+    pub mention_count: i64,
 }
\ No newline at end of file
@@ -0,0 +1,203 @@
+use bitflags::bitflags;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+
+bitflags! {
+    /// Guild permission set, stored as a BIGINT and serialized as a plain integer.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Permissions: i64 {
+        const VIEW_CHANNEL = 1 << 0;
+        const SEND_MESSAGES = 1 << 1;
+        const READ_MESSAGE_HISTORY = 1 << 2;
+        const MANAGE_MESSAGES = 1 << 3;
+        const MANAGE_CHANNELS = 1 << 4;
+        const MANAGE_GUILD = 1 << 5;
+        const MANAGE_ROLES = 1 << 6;
+        const KICK_MEMBERS = 1 << 7;
+        const BAN_MEMBERS = 1 << 8;
+        const CREATE_INVITE = 1 << 9;
+        const MENTION_EVERYONE = 1 << 10;
+        const CONNECT_VOICE = 1 << 11;
+        const SPEAK = 1 << 12;
+        const ADMINISTRATOR = 1 << 13;
+        const ADD_REACTIONS = 1 << 14;
+    }
+}
+
+impl Permissions {
+    /// Permissions granted to the `@everyone` role of a new guild.
+    pub const DEFAULT: Permissions = Permissions::VIEW_CHANNEL
+        .union(Permissions::SEND_MESSAGES)
+        .union(Permissions::READ_MESSAGE_HISTORY)
+        .union(Permissions::CREATE_INVITE)
+        .union(Permissions::CONNECT_VOICE)
+        .union(Permissions::SPEAK)
+        .union(Permissions::ADD_REACTIONS);
+
+    /// Permissions of every recipient in a direct message channel.
+    pub const DM: Permissions = Permissions::VIEW_CHANNEL
+        .union(Permissions::SEND_MESSAGES)
+        .union(Permissions::READ_MESSAGE_HISTORY)
+        .union(Permissions::ADD_REACTIONS);
+
+    /// Resolves the effective permissions of a guild member, optionally within a channel.
+    ///
+    /// `roles` is the union of the `@everyone` role and every role assigned to the member.
+    /// The guild owner and administrators are granted every permission; everyone else gets
+    /// their role permissions with the channel's `@everyone`, role and member overwrites
+    /// applied in that order.
+    pub fn resolve(is_owner: bool, roles: Permissions, overwrites: &ChannelOverwrites) -> Permissions {
+        if is_owner || roles.contains(Permissions::ADMINISTRATOR) {
+            return Permissions::all();
+        }
+
+        [overwrites.everyone, overwrites.roles, overwrites.member]
+            .iter()
+            .fold(roles, |permissions, overwrite| overwrite.apply(permissions))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Overwrite {
+    pub allow: Permissions,
+    pub deny: Permissions,
+}
+
+impl Overwrite {
+    pub fn apply(&self, permissions: Permissions) -> Permissions {
+        permissions.difference(self.deny).union(self.allow)
+    }
+}
+
+/// The overwrites of one channel that apply to one member. Role overwrites are merged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChannelOverwrites {
+    pub everyone: Overwrite,
+    pub roles: Overwrite,
+    pub member: Overwrite,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions::empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum OverwriteType {
+    Role,
+    Member,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionOverwrite {
+    pub channel_id: Uuid,
+    pub target_type: OverwriteType,
+    pub target_id: Uuid,
+    pub allow: Permissions,
+    pub deny: Permissions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetOverwriteRequest {
+    pub target_type: OverwriteType,
+    #[serde(default)]
+    pub allow: Permissions,
+    #[serde(default)]
+    pub deny: Permissions,
+}
+
+impl Serialize for Permissions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for Permissions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        i64::deserialize(deserializer).map(Permissions::from_bits_truncate)
+    }
+}
+
+impl Type<Postgres> for Permissions {
+    fn type_info() -> PgTypeInfo {
+        <i64 as Type<Postgres>>::type_info()
+    }
+}
+
+impl Encode<'_, Postgres> for Permissions {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <i64 as Encode<Postgres>>::encode_by_ref(&self.bits(), buf)
+    }
+}
+
+impl Decode<'_, Postgres> for Permissions {
+    fn decode(value: PgValueRef<'_>) -> Result<Self, BoxDynError> {
+        Ok(Permissions::from_bits_truncate(<i64 as Decode<Postgres>>::decode(value)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overwrite(allow: Permissions, deny: Permissions) -> Overwrite {
+        Overwrite { allow, deny }
+    }
+
+    #[test]
+    fn owner_and_administrator_get_everything() {
+        let overwrites = ChannelOverwrites {
+            everyone: overwrite(Permissions::empty(), Permissions::all()),
+            ..Default::default()
+        };
+
+        assert_eq!(Permissions::resolve(true, Permissions::empty(), &overwrites), Permissions::all());
+        assert_eq!(Permissions::resolve(false, Permissions::ADMINISTRATOR, &overwrites), Permissions::all());
+    }
+
+    #[test]
+    fn without_overwrites_roles_apply() {
+        let permissions = Permissions::resolve(false, Permissions::DEFAULT, &ChannelOverwrites::default());
+
+        assert_eq!(permissions, Permissions::DEFAULT);
+    }
+
+    #[test]
+    fn overwrites_apply_everyone_then_roles_then_member() {
+        let overwrites = ChannelOverwrites {
+            everyone: overwrite(Permissions::empty(), Permissions::SEND_MESSAGES | Permissions::VIEW_CHANNEL),
+            roles: overwrite(Permissions::SEND_MESSAGES, Permissions::ADD_REACTIONS),
+            member: overwrite(Permissions::ADD_REACTIONS, Permissions::SEND_MESSAGES),
+        };
+
+        let permissions = Permissions::resolve(false, Permissions::DEFAULT, &overwrites);
+
+        assert!(!permissions.contains(Permissions::VIEW_CHANNEL));
+        assert!(!permissions.contains(Permissions::SEND_MESSAGES));
+        assert!(permissions.contains(Permissions::ADD_REACTIONS));
+        assert!(permissions.contains(Permissions::READ_MESSAGE_HISTORY));
+    }
+
+    #[test]
+    fn allow_wins_over_deny_within_an_overwrite() {
+        let both = overwrite(Permissions::SEND_MESSAGES, Permissions::SEND_MESSAGES);
+
+        assert!(both.apply(Permissions::empty()).contains(Permissions::SEND_MESSAGES));
+    }
+
+    #[test]
+    fn serializes_as_an_integer_and_ignores_unknown_bits() {
+        let json = serde_json::to_string(&Permissions::DM).unwrap();
+        assert_eq!(json, Permissions::DM.bits().to_string());
+
+        let permissions: Permissions = serde_json::from_str(&(1i64 << 62 | 1).to_string()).unwrap();
+        assert_eq!(permissions, Permissions::VIEW_CHANNEL);
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use sqlx::Type;
+
+/// How a user relates to another, from that user's point of view.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RelationshipType {
+    Friend,
+    PendingIncoming,
+    PendingOutgoing,
+    Blocked,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    Online,
+    Offline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Relationship {
+    pub user_id: Uuid,
+    pub username: String,
+    pub avatar_url: Option<String>,
+    #[serde(rename = "type")]
+    pub relationship_type: RelationshipType,
+    pub presence: Presence,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FriendRequest {
+    pub username: String,
+}
\ No newline at end of file
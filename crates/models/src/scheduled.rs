@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledMessageStatus {
+    Pending,
+    /// Claimed by the delivery task. Cannot be edited or cancelled any more.
+    Sending,
+    /// Delivery was refused, e.g. because the author lost access to the channel.
+    Failed,
+}
+
+/// A message waiting to be posted at `send_at`. Once delivered it becomes a regular
+/// message with the same id and disappears from the schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub content: String,
+    pub reply_to: Option<Uuid>,
+    pub send_at: DateTime<Utc>,
+    pub status: ScheduledMessageStatus,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateScheduledMessageRequest {
+    pub channel_id: Uuid,
+    pub content: String,
+    pub reply_to: Option<Uuid>,
+    pub send_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateScheduledMessageRequest {
+    pub content: Option<String>,
+    pub send_at: Option<DateTime<Utc>>,
+}
\ No newline at end of file
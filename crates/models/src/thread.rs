@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A thread channel nested under a guild channel. Its messages are regular messages
+/// whose `channel_id` is the thread's id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thread {
+    pub id: Uuid,
+    pub guild_id: Uuid,
+    pub parent_id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub starter_message_id: Option<Uuid>,
+    pub member_count: i64,
+    pub message_count: i64,
+    /// Minutes of inactivity after which the thread is archived.
+    pub auto_archive_duration: i32,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub last_activity_at: DateTime<Utc>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMember {
+    pub user_id: Uuid,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateThreadRequest {
+    pub name: String,
+    pub auto_archive_duration: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateThreadRequest {
+    pub name: Option<String>,
+    pub archived: Option<bool>,
+    pub auto_archive_duration: Option<i32>,
+}
\ No newline at end of file
@@ -10,8 +10,15 @@ use tokio::signal;
 use tower_http::trace::{self, TraceLayer};
 use tracing::Level;
 use blazing_auth::{create_auth_routes, AuthService};
-use blazing_chat::{MessagesService, create_chat_routes, WsMessage};
-use blazing_ws::Broadcaster;
+use blazing_chat::{
+    create_chat_routes, create_relationship_routes, DmsService, MessagesService, PermissionsService,
+    RelationshipsService, ScheduledMessagesService, ThreadsService, WsMessage,
+};
+use blazing_guilds::{
+    create_guild_routes, create_invite_routes, ChannelsService, GuildsService, InvitesService, MembersService,
+    RolesService,
+};
+use blazing_ws::{Broadcaster, SessionRegistry};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -36,20 +43,86 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let jwt_secret = env::var("JWT_SECRET")?;
 
     let auth_service = Arc::new(AuthService::new(db_pool.clone(), jwt_secret.clone()));
-    let broadcaster = Arc::new(Broadcaster::<Uuid, WsMessage>::new());
+    let broadcaster = Arc::new(match env::var("WS_BROADCAST_CAPACITY") {
+        Ok(capacity) => {
+            let capacity = capacity
+                .parse()
+                .map_err(|e| format!("WS_BROADCAST_CAPACITY must be a positive integer: {}", e))?;
+            Broadcaster::<Uuid, WsMessage>::with_capacity(capacity)
+        }
+        Err(_) => Broadcaster::new(),
+    });
+    let sessions = Arc::new(SessionRegistry::<Uuid, WsMessage>::new());
+    let permissions_service = Arc::new(PermissionsService::new(db_pool.clone()));
     let messages_service = Arc::new(MessagesService::new(
         db_pool.clone(),
-        broadcaster.clone()
+        broadcaster.clone(),
+        permissions_service.clone(),
+        sessions.clone()
+    ));
+    let dms_service = Arc::new(DmsService::new(
+        db_pool.clone(),
+        messages_service.clone(),
+        sessions.clone()
+    ));
+    let threads_service = Arc::new(ThreadsService::new(
+        db_pool.clone(),
+        permissions_service.clone(),
+        broadcaster.clone(),
+        sessions.clone()
+    ));
+    tokio::spawn(threads_service.clone().run_auto_archive());
+    let scheduled_service = Arc::new(ScheduledMessagesService::new(db_pool.clone(), messages_service.clone()));
+    tokio::spawn(scheduled_service.clone().run_delivery());
+    let relationships_service = Arc::new(RelationshipsService::new(db_pool.clone(), sessions.clone()));
+    let guilds_service = Arc::new(GuildsService::new(
+        db_pool.clone(),
+        permissions_service.clone(),
+        sessions.clone()
+    ));
+    let channels_service = Arc::new(ChannelsService::new(
+        db_pool.clone(),
+        permissions_service.clone(),
+        sessions.clone()
+    ));
+    let invites_service = Arc::new(InvitesService::new(
+        db_pool.clone(),
+        messages_service.clone(),
+        permissions_service.clone(),
+        sessions.clone()
+    ));
+    let members_service = Arc::new(MembersService::new(
+        db_pool.clone(),
+        messages_service.clone(),
+        permissions_service.clone(),
+        sessions.clone()
+    ));
+    let roles_service = Arc::new(RolesService::new(
+        db_pool.clone(),
+        permissions_service,
+        sessions.clone()
     ));
-
-    let broadcaster = Arc::new(Broadcaster::<Uuid, WsMessage>::new());
 
     let api_routes = Router::new()
         .nest("/auth", create_auth_routes(auth_service.clone()))
+        .nest("/guilds", create_guild_routes(
+            guilds_service,
+            channels_service,
+            invites_service.clone(),
+            members_service,
+            roles_service,
+            auth_service.clone(),
+        ))
+        .nest("/invites", create_invite_routes(invites_service, auth_service.clone()))
+        .nest("/relationships", create_relationship_routes(relationships_service, auth_service.clone()))
         .nest("/chat", create_chat_routes(
             messages_service,
+            dms_service,
+            threads_service,
+            scheduled_service,
             auth_service.clone(),
             broadcaster,
+            sessions,
         ));
 
     let app = Router::new()
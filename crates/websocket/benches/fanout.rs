@@ -0,0 +1,197 @@
+//! Fan-out benchmark for the WebSocket send path.
+//!
+//! Connects many clients to a local server, then measures the server process's CPU
+//! time while every client sits idle, and the delivery latency and CPU time while
+//! messages are broadcast to all of them.
+//!
+//! ```text
+//! cargo bench -p blazing-ws --bench fanout
+//! BENCH_CLIENTS=5000 BENCH_MESSAGES=500 cargo bench -p blazing-ws --bench fanout
+//! ```
+//!
+//! Clients run in the same process as the server, so the busy CPU figure includes
+//! the cost of receiving. Raise the open file limit for large client counts.
+
+use async_trait::async_trait;
+use axum::Router;
+use blazing_ws::{ws_routes, Broadcaster, ClientId, MessageHandler, MessageOutcome, Result, SessionRegistry, WsState};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+/// Key every client listens to, besides its own user id.
+const LOBBY: Uuid = Uuid::nil();
+const IDLE_PERIOD: Duration = Duration::from_secs(5);
+const BROADCAST_INTERVAL: Duration = Duration::from_millis(50);
+const CONNECT_BATCH: usize = 100;
+/// Linux reports process times in clock ticks, which are 10ms on every common configuration.
+const CLOCK_TICK: Duration = Duration::from_millis(10);
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+fn since_epoch() -> Duration {
+    EPOCH.get_or_init(Instant::now).elapsed()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BenchMessage {
+    Ping,
+    Stamp { sent_at_nanos: u64 },
+}
+
+#[derive(Clone)]
+struct BenchHandler;
+
+#[async_trait]
+impl MessageHandler for BenchHandler {
+    type Message = BenchMessage;
+    type BroadcastKey = Uuid;
+
+    async fn authenticate(&self, token: &str) -> Result<Uuid> {
+        Ok(token.parse()?)
+    }
+
+    async fn on_connect(&self, _client_id: ClientId, _user_id: Uuid) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_disconnect(&self, _client_id: ClientId) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_message(
+        &self,
+        _client_id: ClientId,
+        _user_id: Uuid,
+        _message: BenchMessage,
+    ) -> Result<MessageOutcome<Uuid, BenchMessage>> {
+        Ok(MessageOutcome::none())
+    }
+
+    async fn get_user_broadcast_keys(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        Ok(vec![LOBBY, user_id])
+    }
+}
+
+fn env_or(name: &str, default: usize) -> usize {
+    std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+/// User plus system CPU time consumed by this process so far.
+fn process_cpu_time() -> Duration {
+    let stat = std::fs::read_to_string("/proc/self/stat").expect("read /proc/self/stat");
+    // The command name may contain spaces, so count fields from after its closing parenthesis.
+    let fields: Vec<&str> = stat[stat.rfind(')').expect("malformed stat") + 2..].split(' ').collect();
+    let ticks: u32 = fields[11].parse::<u32>().unwrap() + fields[12].parse::<u32>().unwrap();
+    CLOCK_TICK * ticks
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+async fn connect_client(url: String, latencies: mpsc::UnboundedSender<Duration>) {
+    let (stream, _) = tokio_tungstenite::connect_async(url).await.expect("connect");
+    let (_write, mut read) = stream.split();
+
+    tokio::spawn(async move {
+        while let Some(Ok(frame)) = read.next().await {
+            if let Message::Text(text) = frame
+                && let Ok(BenchMessage::Stamp { sent_at_nanos }) = serde_json::from_str(&text)
+            {
+                let _ = latencies.send(since_epoch() - Duration::from_nanos(sent_at_nanos));
+            }
+        }
+    });
+}
+
+#[tokio::main]
+async fn main() {
+    let clients = env_or("BENCH_CLIENTS", 2000);
+    let messages = env_or("BENCH_MESSAGES", 100);
+    since_epoch();
+
+    let broadcasts = Broadcaster::new();
+    let state = WsState::new(BenchHandler, broadcasts, SessionRegistry::new());
+    let broadcasts = state.broadcasts.clone();
+    let app: Router = ws_routes::<BenchHandler>().with_state(state);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let (latency_tx, mut latency_rx) = mpsc::unbounded_channel();
+    let started = Instant::now();
+    for batch in (0..clients).collect::<Vec<_>>().chunks(CONNECT_BATCH) {
+        futures::future::join_all(batch.iter().map(|_| {
+            connect_client(format!("ws://{}/ws?token={}", addr, Uuid::new_v4()), latency_tx.clone())
+        })).await;
+    }
+    drop(latency_tx);
+
+    // Connections finish subscribing in the background; wait until they all listen.
+    while broadcasts.broadcast(&LOBBY, BenchMessage::Ping).await.unwrap() < clients {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    println!("{} clients connected in {:.2?}", clients, started.elapsed());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let cpu_before = process_cpu_time();
+    tokio::time::sleep(IDLE_PERIOD).await;
+    let idle_cpu = process_cpu_time() - cpu_before;
+    println!(
+        "idle:  {:.2?} CPU over {:.2?} ({:.1}% of a core)",
+        idle_cpu, IDLE_PERIOD, 100.0 * idle_cpu.as_secs_f64() / IDLE_PERIOD.as_secs_f64()
+    );
+
+    let expected = clients * messages;
+    let cpu_before = process_cpu_time();
+    let started = Instant::now();
+    let collector = tokio::spawn(async move {
+        let mut latencies = Vec::with_capacity(expected);
+        let deadline = tokio::time::sleep(Duration::from_secs(30) + BROADCAST_INTERVAL * messages as u32);
+        tokio::pin!(deadline);
+        while latencies.len() < expected {
+            tokio::select! {
+                Some(latency) = latency_rx.recv() => latencies.push(latency),
+                _ = &mut deadline => break,
+            }
+        }
+        latencies
+    });
+
+    let mut interval = tokio::time::interval(BROADCAST_INTERVAL);
+    for _ in 0..messages {
+        interval.tick().await;
+        let sent_at_nanos = since_epoch().as_nanos() as u64;
+        broadcasts.broadcast(&LOBBY, BenchMessage::Stamp { sent_at_nanos }).await.unwrap();
+    }
+
+    let mut latencies = collector.await.unwrap();
+    let elapsed = started.elapsed();
+    let busy_cpu = process_cpu_time() - cpu_before;
+    latencies.sort();
+
+    println!(
+        "busy:  {} messages to {} clients, {}/{} delivered in {:.2?}",
+        messages, clients, latencies.len(), expected, elapsed
+    );
+    println!(
+        "       {:.2?} CPU ({:.1}% of a core)",
+        busy_cpu, 100.0 * busy_cpu.as_secs_f64() / elapsed.as_secs_f64()
+    );
+    if !latencies.is_empty() {
+        println!(
+            "       latency p50 {:.2?}, p99 {:.2?}, max {:.2?}",
+            percentile(&latencies, 0.50), percentile(&latencies, 0.99), latencies[latencies.len() - 1]
+        );
+    }
+}
\ No newline at end of file
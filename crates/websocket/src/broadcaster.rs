@@ -1,7 +1,12 @@
 use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 
+/// Messages buffered per key when no capacity is given. A subscriber that falls
+/// further behind than the capacity misses messages and is told it lagged.
+pub const DEFAULT_BROADCAST_CAPACITY: NonZeroUsize = NonZeroUsize::new(100).unwrap();
+
 #[derive(Clone)]
 pub struct Broadcaster<K, V>
 where
@@ -9,7 +14,7 @@ where
     V: Clone,
 {
     channels: Arc<RwLock<HashMap<K, broadcast::Sender<V>>>>,
-    capacity: usize,
+    capacity: NonZeroUsize,
 }
 
 impl<K, V> Broadcaster<K, V>
@@ -18,9 +23,13 @@ where
     V: Clone,
 {
     pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_BROADCAST_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: NonZeroUsize) -> Self {
         Self {
             channels: Arc::new(RwLock::new(HashMap::new())),
-            capacity: 100,
+            capacity,
         }
     }
 
@@ -29,7 +38,7 @@ where
 
         let sender = channels
             .entry(key.clone())
-            .or_insert_with(|| broadcast::channel(self.capacity).0);
+            .or_insert_with(|| broadcast::channel(self.capacity.get()).0);
 
         sender.subscribe()
     }
@@ -50,4 +59,14 @@ where
             Ok(0)
         }
     }
+}
+
+impl<K, V> Default for Broadcaster<K, V>
+where
+    K: std::hash::Hash + Eq + Clone,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::time::Duration;
+
+/// The client message could not be parsed or failed validation.
+pub const INVALID_MESSAGE: &str = "invalid_message";
+/// The handler failed without saying why.
+pub const INTERNAL_ERROR: &str = "internal_error";
+
+/// A client message together with the nonce the client uses to match replies to it.
+/// The nonce sits next to the message's own fields, so messages without one are
+/// accepted unchanged.
+#[derive(Deserialize)]
+pub(crate) struct Envelope<M> {
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub message: M,
+}
+
+/// Recovers the nonce of a message that failed to parse.
+#[derive(Deserialize)]
+pub(crate) struct NonceOnly {
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+/// A frame sent only to the client whose message it answers.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Reply<V> {
+    /// The message was handled. `data` is the handler's reply, if it had one.
+    Ack {
+        nonce: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<V>,
+    },
+    Error {
+        nonce: Option<String>,
+        #[serde(flatten)]
+        error: MessageError,
+    },
+}
+
+/// An error reported to the client that sent the message. Handlers return it from
+/// [`crate::MessageHandler::on_message`] or [`crate::MessageHandler::validate_message`]
+/// to choose the code and text the client sees; any other error is reported as
+/// [`INTERNAL_ERROR`].
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageError {
+    pub code: String,
+    pub message: String,
+    /// When the client may try again, for rate limits. Serialized in seconds.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_secs")]
+    pub retry_after: Option<Duration>,
+}
+
+impl MessageError {
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// Extracts a `MessageError` returned by a handler, or builds one with
+    /// `fallback_code` and `fallback_message` for any other error.
+    pub(crate) fn from_handler(
+        error: Box<dyn std::error::Error + Send + Sync>,
+        fallback_code: &str,
+        fallback_message: &str,
+    ) -> Self {
+        match error.downcast::<MessageError>() {
+            Ok(error) => *error,
+            Err(_) => Self::new(fallback_code, fallback_message),
+        }
+    }
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+fn serialize_secs<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_f64(duration.as_secs_f64()),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// Shaped like a handler's message type: internally tagged, with struct and newtype variants.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum TestMessage {
+        Typing { channel_id: u32 },
+        Send(Payload),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Payload {
+        content: String,
+    }
+
+    fn to_value<V: Serialize>(value: &V) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn envelope_without_a_nonce() {
+        let envelope: Envelope<TestMessage> = serde_json::from_str(r#"{"type":"typing","channel_id":1}"#).unwrap();
+
+        assert_eq!(envelope.nonce, None);
+        assert_eq!(envelope.message, TestMessage::Typing { channel_id: 1 });
+    }
+
+    #[test]
+    fn envelope_with_a_nonce() {
+        let envelope: Envelope<TestMessage> =
+            serde_json::from_str(r#"{"nonce":"n1","type":"send","content":"hi"}"#).unwrap();
+
+        assert_eq!(envelope.nonce.as_deref(), Some("n1"));
+        assert_eq!(envelope.message, TestMessage::Send(Payload { content: "hi".to_string() }));
+    }
+
+    #[test]
+    fn nonce_survives_an_invalid_message() {
+        let text = r#"{"nonce":"n2","type":"unknown"}"#;
+
+        assert!(serde_json::from_str::<Envelope<TestMessage>>(text).is_err());
+        assert_eq!(serde_json::from_str::<NonceOnly>(text).unwrap().nonce.as_deref(), Some("n2"));
+    }
+
+    #[test]
+    fn serializes_acks() {
+        let ack = Reply::Ack { nonce: Some("n1".to_string()), data: Some(TestMessage::Typing { channel_id: 1 }) };
+        assert_eq!(
+            to_value(&ack),
+            json!({"type": "ack", "nonce": "n1", "data": {"type": "typing", "channel_id": 1}})
+        );
+
+        let empty = Reply::<TestMessage>::Ack { nonce: Some("n1".to_string()), data: None };
+        assert_eq!(to_value(&empty), json!({"type": "ack", "nonce": "n1"}));
+    }
+
+    #[test]
+    fn serializes_errors() {
+        let error = Reply::<TestMessage>::Error { nonce: None, error: MessageError::new("forbidden", "No") };
+        assert_eq!(
+            to_value(&error),
+            json!({"type": "error", "nonce": null, "code": "forbidden", "message": "No"})
+        );
+
+        let rate_limited = Reply::<TestMessage>::Error {
+            nonce: Some("n1".to_string()),
+            error: MessageError::new("rate_limited", "Slow down").with_retry_after(Duration::from_millis(1500)),
+        };
+        assert_eq!(
+            to_value(&rate_limited),
+            json!({"type": "error", "nonce": "n1", "code": "rate_limited", "message": "Slow down", "retry_after": 1.5})
+        );
+    }
+
+    #[test]
+    fn from_handler_keeps_message_errors() {
+        let error = MessageError::new("not_found", "Gone").with_retry_after(Duration::from_secs(2));
+        let extracted = MessageError::from_handler(Box::new(error), INTERNAL_ERROR, "Internal server error");
+
+        assert_eq!(extracted.code, "not_found");
+        assert_eq!(extracted.message, "Gone");
+        assert_eq!(extracted.retry_after, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn from_handler_falls_back_for_other_errors() {
+        let error = std::io::Error::other("disk on fire");
+        let extracted = MessageError::from_handler(Box::new(error), INTERNAL_ERROR, "Internal server error");
+
+        assert_eq!(extracted.code, INTERNAL_ERROR);
+        assert_eq!(extracted.message, "Internal server error");
+        assert_eq!(extracted.retry_after, None);
+    }
+}
@@ -28,6 +28,7 @@ where
             socket,
             client_id,
             state.broadcasts,
+            state.sessions,
             state.handler,
             Some(query.token),
         )
@@ -2,11 +2,15 @@ mod handlers;
 mod routes;
 mod service;
 mod broadcaster;
+mod sessions;
+mod frames;
 
 pub use handlers::ws_handler;
 pub use routes::ws_routes;
-pub use service::{WebSocketService, MessageHandler};
-pub use broadcaster::Broadcaster;
+pub use service::{WebSocketService, MessageHandler, MessageOutcome, LAGGED_CLOSE_CODE};
+pub use frames::{MessageError, INTERNAL_ERROR, INVALID_MESSAGE};
+pub use broadcaster::{Broadcaster, DEFAULT_BROADCAST_CAPACITY};
+pub use sessions::{SessionRegistry, SessionCommand};
 
 use std::sync::Arc;
 use uuid::Uuid;
@@ -22,6 +26,7 @@ where
 {
     pub handler: Arc<H>,
     pub broadcasts: Arc<Broadcaster<K, V>>,
+    pub sessions: Arc<SessionRegistry<K, V>>,
 }
 
 impl<H, K, V> WsState<H, K, V>
@@ -29,10 +34,11 @@ where
     K: std::hash::Hash + Eq + Clone,
     V: Clone,
 {
-    pub fn new(handler: H, broadcasts: Broadcaster<K, V>) -> Self {
+    pub fn new(handler: H, broadcasts: Broadcaster<K, V>, sessions: SessionRegistry<K, V>) -> Self {
         Self {
             handler: Arc::new(handler),
             broadcasts: Arc::new(broadcasts),
+            sessions: Arc::new(sessions),
         }
     }
 }
\ No newline at end of file
@@ -1,9 +1,22 @@
-use crate::{ClientId, Result, Broadcaster};
-use axum::extract::ws::{Message, WebSocket};
+use crate::{ClientId, Result, Broadcaster, SessionCommand, SessionRegistry};
+use crate::frames::{Envelope, MessageError, NonceOnly, Reply, INTERNAL_ERROR, INVALID_MESSAGE};
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use serde::{Serialize, de::DeserializeOwned};
 use async_trait::async_trait;
+use futures::stream::SplitSink;
 use futures::{StreamExt, SinkExt};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+/// Broadcasts waiting to be written to a single connection.
+const OUTGOING_BUFFER: usize = 256;
+
+/// Close code sent when a connection fell behind its broadcasts and the handler
+/// did not provide a message asking the client to resync.
+pub const LAGGED_CLOSE_CODE: u16 = 4008;
 
 #[async_trait]
 pub trait MessageHandler: Send + Sync + 'static {
@@ -13,61 +26,236 @@ pub trait MessageHandler: Send + Sync + 'static {
     async fn authenticate(&self, token: &str) -> Result<uuid::Uuid>;
     async fn on_connect(&self, client_id: ClientId, user_id: uuid::Uuid) -> Result<()>;
     async fn on_disconnect(&self, client_id: ClientId) -> Result<()>;
+    /// Handles a message from the client. Return a [`MessageError`] to choose the `error`
+    /// frame the client receives.
     async fn on_message(
         &self,
         client_id: ClientId,
         user_id: uuid::Uuid,
         message: Self::Message,
-    ) -> Result<Option<(Self::BroadcastKey, Self::Message)>>;
+    ) -> Result<MessageOutcome<Self::BroadcastKey, Self::Message>>;
+    /// The keys a new connection starts out subscribed to. Later changes are pushed to
+    /// live sessions through [`SessionRegistry`]. Once a key is unsubscribed, broadcasts
+    /// on it that are still queued for the connection are dropped rather than delivered.
     async fn get_user_broadcast_keys(&self, user_id: uuid::Uuid) -> Result<Vec<Self::BroadcastKey>>;
     async fn validate_message(&self, _message: &Self::Message) -> Result<()> {
         Ok(())
     }
+
+    /// Called when the connection missed broadcasts on `keys` because it fell behind.
+    /// Returns the message telling the client what to re-fetch, or `None` to close the
+    /// connection with [`LAGGED_CLOSE_CODE`] so the client reconnects from scratch.
+    async fn on_lagged(
+        &self,
+        _client_id: ClientId,
+        _user_id: uuid::Uuid,
+        _keys: Vec<Self::BroadcastKey>,
+    ) -> Result<Option<Self::Message>> {
+        Ok(None)
+    }
+}
+
+/// What happens after a client message was handled.
+pub struct MessageOutcome<K, V> {
+    /// Sent to every connection subscribed to the key.
+    pub broadcast: Option<(K, V)>,
+    /// Returned in the `ack` frame when the client sent the message with a nonce.
+    pub reply: Option<V>,
+}
+
+impl<K, V> MessageOutcome<K, V> {
+    pub fn none() -> Self {
+        Self { broadcast: None, reply: None }
+    }
+
+    pub fn broadcast(key: K, message: V) -> Self {
+        Self { broadcast: Some((key, message)), reply: None }
+    }
+
+    pub fn reply(message: V) -> Self {
+        Self { broadcast: None, reply: Some(message) }
+    }
+}
+
+/// What a forwarding task or the receiving side hands to the connection. Broadcasts
+/// carry the key and subscription they arrived on, so that those queued before an
+/// unsubscribe can be told apart from a later subscription to the same key.
+enum Outgoing<K, V> {
+    Message(K, SubscriptionId, V),
+    /// Broadcasts on the key were dropped before they could be forwarded.
+    Lagged(K, SubscriptionId),
+    /// An answer to one of the client's own messages.
+    Reply(Reply<V>),
+}
+
+type SubscriptionId = u64;
+
+struct Forwarder {
+    id: SubscriptionId,
+    task: JoinHandle<()>,
+}
+
+/// The broadcast keys a connection listens to. Each key gets a forwarding task that
+/// waits on its broadcast receiver and feeds a single per-connection queue, so the
+/// connection only wakes up when one of its keys has something to send.
+struct Subscriptions<K, V> {
+    outgoing: mpsc::Sender<Outgoing<K, V>>,
+    forwarders: HashMap<K, Forwarder>,
+    next_id: SubscriptionId,
+}
+
+impl<K, V> Subscriptions<K, V>
+where
+    K: std::hash::Hash + Eq + Clone + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    fn new(outgoing: mpsc::Sender<Outgoing<K, V>>) -> Self {
+        Self {
+            outgoing,
+            forwarders: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    async fn subscribe(&mut self, broadcasts: &Broadcaster<K, V>, key: K) {
+        if let Entry::Vacant(entry) = self.forwarders.entry(key) {
+            let id = self.next_id;
+            self.next_id += 1;
+
+            let receiver = broadcasts.subscribe(entry.key()).await;
+            let task = tokio::spawn(forward(entry.key().clone(), id, receiver, self.outgoing.clone()));
+            entry.insert(Forwarder { id, task });
+        }
+    }
+
+    fn unsubscribe(&mut self, key: &K) {
+        if let Some(forwarder) = self.forwarders.remove(key) {
+            forwarder.task.abort();
+        }
+    }
+
+    /// Whether a broadcast forwarded by subscription `id` to `key` should still be delivered.
+    fn is_current(&self, key: &K, id: SubscriptionId) -> bool {
+        self.forwarders.get(key).is_some_and(|forwarder| forwarder.id == id)
+    }
+}
+
+/// Moves broadcasts from one key into the connection's queue until either side closes.
+/// The queue is bounded, so a connection that cannot keep up stalls its forwarders
+/// until the broadcast channel overwrites messages they have not read yet. Forwarding
+/// carries on after a lag, so the client keeps receiving new events while it resyncs.
+async fn forward<K: Clone, V: Clone>(
+    key: K,
+    id: SubscriptionId,
+    mut receiver: broadcast::Receiver<V>,
+    outgoing: mpsc::Sender<Outgoing<K, V>>,
+) {
+    loop {
+        let next = match receiver.recv().await {
+            Ok(msg) => Outgoing::Message(key.clone(), id, msg),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Connection lagged behind, skipped {} messages", skipped);
+                Outgoing::Lagged(key.clone(), id)
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        if outgoing.send(next).await.is_err() {
+            return;
+        }
+    }
+}
+
+impl<K, V> Drop for Subscriptions<K, V> {
+    fn drop(&mut self) {
+        for forwarder in self.forwarders.values() {
+            forwarder.task.abort();
+        }
+    }
+}
+
+/// Writes `msg` as a text frame. Messages that fail to serialize are skipped; returns
+/// `false` once the socket is closed.
+async fn send_json<V: Serialize>(sender: &mut SplitSink<WebSocket, Message>, msg: &V) -> bool {
+    let Ok(json) = serde_json::to_string(msg) else {
+        return true;
+    };
+
+    sender.send(Message::Text(json.into())).await.is_ok()
+}
+
+async fn close_lagged(sender: &mut SplitSink<WebSocket, Message>, client_id: ClientId) {
+    tracing::warn!("Closing lagged connection for client {}", client_id);
+    let _ = sender.send(Message::Close(Some(CloseFrame {
+        code: LAGGED_CLOSE_CODE,
+        reason: "Connection fell behind, reconnect to resync".into(),
+    }))).await;
 }
 
 pub struct WebSocketService;
 
 impl WebSocketService {
+    /// Handles one client message. Failures are answered with an `error` frame; success
+    /// is answered with an `ack` frame only when the client sent a nonce.
     async fn handle_incoming_message<H: MessageHandler>(
         client_id: ClientId,
         user_id: uuid::Uuid,
         text: &str,
         broadcasts: &Arc<Broadcaster<H::BroadcastKey, H::Message>>,
         handler: &Arc<H>,
+        replies: &mpsc::Sender<Outgoing<H::BroadcastKey, H::Message>>,
     ) {
-        let parsed_msg = match serde_json::from_str::<H::Message>(text) {
-            Ok(msg) => msg,
+        let Envelope { nonce, message } = match serde_json::from_str::<Envelope<H::Message>>(text) {
+            Ok(envelope) => envelope,
             Err(e) => {
                 tracing::warn!(
                     "Failed to parse WebSocket message from client {}: {} - raw: {}",
                     client_id, e, text
                 );
+                let nonce = serde_json::from_str::<NonceOnly>(text).ok().and_then(|n| n.nonce);
+                let error = MessageError::new(INVALID_MESSAGE, e.to_string());
+                let _ = replies.send(Outgoing::Reply(Reply::Error { nonce, error })).await;
                 return;
             }
         };
 
-        if handler.validate_message(&parsed_msg).await.is_err() {
+        if let Err(e) = handler.validate_message(&message).await {
+            let error = MessageError::from_handler(e, INVALID_MESSAGE, "Invalid message");
+            let _ = replies.send(Outgoing::Reply(Reply::Error { nonce, error })).await;
             return;
         }
 
-        let (broadcast_key, message_to_send) = match handler.on_message(client_id, user_id, parsed_msg).await {
-            Ok(Some((key, msg))) => (key, msg),
-            Ok(None) => return,
+        let outcome = match handler.on_message(client_id, user_id, message).await {
+            Ok(outcome) => outcome,
             Err(e) => {
-                tracing::error!("Error handling message: {}", e);
+                // A `MessageError` is an expected refusal; the handler logs its own failures.
+                if e.is::<MessageError>() {
+                    tracing::debug!("Refused message from client {}: {}", client_id, e);
+                } else {
+                    tracing::error!("Error handling message: {}", e);
+                }
+                let error = MessageError::from_handler(e, INTERNAL_ERROR, "Internal server error");
+                let _ = replies.send(Outgoing::Reply(Reply::Error { nonce, error })).await;
                 return;
             }
         };
 
-        if let Err(e) = broadcasts.broadcast(&broadcast_key, message_to_send).await {
+        if let Some((key, msg)) = outcome.broadcast
+            && let Err(e) = broadcasts.broadcast(&key, msg).await
+        {
             tracing::error!("Broadcast failed: {}", e);
         }
+
+        if nonce.is_some() {
+            let _ = replies.send(Outgoing::Reply(Reply::Ack { nonce, data: outcome.reply })).await;
+        }
     }
 
     pub async fn handle_socket<H: MessageHandler>(
         socket: WebSocket,
         client_id: ClientId,
         broadcasts: Arc<Broadcaster<H::BroadcastKey, H::Message>>,
+        sessions: Arc<SessionRegistry<H::BroadcastKey, H::Message>>,
         handler: Arc<H>,
         token: Option<String>,
     ) {
@@ -92,34 +280,83 @@ impl WebSocketService {
             return;
         }
 
+        // Register before loading the initial keys so that subscriptions issued in between are not lost.
+        let mut commands = sessions.register(user_id, client_id).await;
+
         let user_channels = match handler.get_user_broadcast_keys(user_id).await {
             Ok(keys) => keys,
             Err(e) => {
                 tracing::error!("Failed to get broadcast keys for user {}: {}", user_id, e);
+                sessions.unregister(user_id, client_id).await;
                 return;
             }
         };
 
-        let mut receivers = Vec::new();
+        let (outgoing, mut outgoing_rx) = mpsc::channel(OUTGOING_BUFFER);
+        let replies = outgoing.clone();
+        let mut subscriptions = Subscriptions::new(outgoing);
         for key in user_channels {
-            receivers.push(broadcasts.subscribe(&key).await);
+            subscriptions.subscribe(&broadcasts, key).await;
         }
 
+        // Sleeps until a broadcast or a subscription change arrives, so idle
+        // connections cost nothing.
+        let broadcasts_send = broadcasts.clone();
+        let handler_send = handler.clone();
         let mut send_task = tokio::spawn(async move {
             loop {
-                let mut received = false;
-                for rx in &mut receivers {
-                    if let Ok(msg) = rx.try_recv() {
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if sender.send(Message::Text(json.into())).await.is_err() {
+                tokio::select! {
+                    biased;
+
+                    command = commands.recv() => match command {
+                        Some(SessionCommand::Subscribe(keys)) => {
+                            for key in keys {
+                                subscriptions.subscribe(&broadcasts_send, key).await;
+                            }
+                        }
+                        Some(SessionCommand::Unsubscribe(keys)) => {
+                            for key in keys {
+                                subscriptions.unsubscribe(&key);
+                            }
+                        }
+                        Some(SessionCommand::Deliver(msg)) => {
+                            if !send_json(&mut sender, &msg).await {
                                 return;
                             }
-                            received = true;
+                        }
+                        // Evicted from the registry for falling behind on its commands.
+                        None => {
+                            close_lagged(&mut sender, client_id).await;
+                            return;
+                        }
+                    },
+
+                    Some(next) = outgoing_rx.recv() => {
+                        let msg = match next {
+                            Outgoing::Message(key, id, _) | Outgoing::Lagged(key, id)
+                                if !subscriptions.is_current(&key, id) => continue,
+                            Outgoing::Message(_, _, msg) => msg,
+                            Outgoing::Lagged(key, _) => match handler_send.on_lagged(client_id, user_id, vec![key]).await {
+                                Ok(Some(msg)) => msg,
+                                Ok(None) | Err(_) => {
+                                    close_lagged(&mut sender, client_id).await;
+                                    return;
+                                }
+                            },
+                            Outgoing::Reply(reply) => {
+                                if !send_json(&mut sender, &reply).await {
+                                    return;
+                                }
+                                continue;
+                            }
+                        };
+
+                        if !send_json(&mut sender, &msg).await {
+                            return;
                         }
                     }
-                }
-                if !received {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+                    else => return,
                 }
             }
         });
@@ -135,6 +372,7 @@ impl WebSocketService {
                         &text,
                         &broadcasts_clone,
                         &handler_clone,
+                        &replies,
                     ).await;
                 }
             }
@@ -145,6 +383,8 @@ impl WebSocketService {
             _ = &mut recv_task => send_task.abort(),
         }
 
+        sessions.unregister(user_id, client_id).await;
+
         tracing::info!("Client disconnected: {}", client_id);
         let _ = handler.on_disconnect(client_id).await;
     }
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, RwLock};
+use uuid::Uuid;
+use crate::ClientId;
+
+/// Commands waiting to be applied by a single session. A session that lets this many
+/// pile up is evicted from the registry and closes with
+/// [`LAGGED_CLOSE_CODE`](crate::LAGGED_CLOSE_CODE), so the client reconnects and resyncs.
+const SESSION_COMMAND_BUFFER: usize = 256;
+
+/// An instruction for a live session.
+pub enum SessionCommand<K, V> {
+    Subscribe(Vec<K>),
+    Unsubscribe(Vec<K>),
+    /// Writes a message to the session directly, outside of any broadcast key.
+    Deliver(V),
+}
+
+type UserSessions<K, V> = HashMap<ClientId, mpsc::Sender<SessionCommand<K, V>>>;
+
+struct Sessions<K, V> {
+    by_user: HashMap<Uuid, UserSessions<K, V>>,
+    /// The user behind each live client.
+    users: HashMap<ClientId, Uuid>,
+}
+
+/// The live sessions of each user, used to change their subscriptions without a
+/// reconnect and to send events to particular users or clients. Sessions are
+/// registered before their initial keys are loaded, so a command sent while a
+/// connection is starting up is applied after those keys.
+#[derive(Clone)]
+pub struct SessionRegistry<K, V>
+where
+    K: std::hash::Hash + Eq + Clone,
+    V: Clone,
+{
+    sessions: Arc<RwLock<Sessions<K, V>>>,
+}
+
+impl<K, V> SessionRegistry<K, V>
+where
+    K: std::hash::Hash + Eq + Clone,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            sessions: Arc::new(RwLock::new(Sessions {
+                by_user: HashMap::new(),
+                users: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Registers a session. The returned receiver yields `None` once the session has been
+    /// evicted for not keeping up with its commands.
+    pub async fn register(&self, user_id: Uuid, client_id: ClientId) -> mpsc::Receiver<SessionCommand<K, V>> {
+        let (tx, rx) = mpsc::channel(SESSION_COMMAND_BUFFER);
+
+        let mut sessions = self.sessions.write().await;
+        sessions.by_user.entry(user_id).or_default().insert(client_id, tx);
+        sessions.users.insert(client_id, user_id);
+
+        rx
+    }
+
+    pub async fn unregister(&self, user_id: Uuid, client_id: ClientId) {
+        let mut sessions = self.sessions.write().await;
+        sessions.remove(user_id, client_id);
+    }
+
+    /// Drops the sessions whose command queue is full.
+    async fn evict(&self, full: &[(Uuid, ClientId)]) {
+        if full.is_empty() {
+            return;
+        }
+
+        let mut sessions = self.sessions.write().await;
+        for &(user_id, client_id) in full {
+            tracing::warn!("Evicting session {} of user {}: too many pending commands", client_id, user_id);
+            sessions.remove(user_id, client_id);
+        }
+    }
+
+    /// The subset of `user_ids` that have at least one live session.
+    pub async fn online_users(&self, user_ids: &[Uuid]) -> HashSet<Uuid> {
+        let sessions = self.sessions.read().await;
+
+        user_ids
+            .iter()
+            .filter(|user_id| sessions.by_user.contains_key(user_id))
+            .copied()
+            .collect()
+    }
+
+    /// Sends `message` to a single client. Returns whether the client is still live.
+    pub async fn send_to_client(&self, client_id: ClientId, message: V) -> bool {
+        let user_id = self.sessions.read().await.users.get(&client_id).copied();
+
+        match user_id {
+            Some(user_id) => self.command_client(user_id, client_id, SessionCommand::Deliver(message)).await,
+            None => false,
+        }
+    }
+
+    /// Sends `message` to every live session of `user_id`.
+    /// Returns the number of sessions it was sent to.
+    pub async fn send_to_user(&self, user_id: Uuid, message: V) -> usize {
+        self.send_to_users(&[user_id], message).await
+    }
+
+    /// Sends `message` to every live session of the given users.
+    /// Returns the number of sessions it was sent to.
+    pub async fn send_to_users(&self, user_ids: &[Uuid], message: V) -> usize {
+        self.command_users(user_ids, || SessionCommand::Deliver(message.clone())).await
+    }
+
+    /// Subscribes every live session of the given users to `keys`.
+    /// Returns the number of sessions that were notified.
+    pub async fn subscribe_users(&self, user_ids: &[Uuid], keys: &[K]) -> usize {
+        self.command_users(user_ids, || SessionCommand::Subscribe(keys.to_vec())).await
+    }
+
+    /// Unsubscribes every live session of the given users from `keys`.
+    /// Returns the number of sessions that were notified.
+    pub async fn unsubscribe_users(&self, user_ids: &[Uuid], keys: &[K]) -> usize {
+        self.command_users(user_ids, || SessionCommand::Unsubscribe(keys.to_vec())).await
+    }
+
+    /// Subscribes a single session to `keys`. Returns whether the session is still live.
+    pub async fn subscribe_client(&self, user_id: Uuid, client_id: ClientId, keys: &[K]) -> bool {
+        self.command_client(user_id, client_id, SessionCommand::Subscribe(keys.to_vec())).await
+    }
+
+    /// Unsubscribes a single session from `keys`. Returns whether the session is still live.
+    pub async fn unsubscribe_client(&self, user_id: Uuid, client_id: ClientId, keys: &[K]) -> bool {
+        self.command_client(user_id, client_id, SessionCommand::Unsubscribe(keys.to_vec())).await
+    }
+
+    async fn command_client(&self, user_id: Uuid, client_id: ClientId, command: SessionCommand<K, V>) -> bool {
+        let result = {
+            let sessions = self.sessions.read().await;
+            match sessions.by_user.get(&user_id).and_then(|clients| clients.get(&client_id)) {
+                Some(sender) => sender.try_send(command),
+                None => return false,
+            }
+        };
+
+        match result {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.evict(&[(user_id, client_id)]).await;
+                false
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    async fn command_users<F>(&self, user_ids: &[Uuid], command: F) -> usize
+    where
+        F: Fn() -> SessionCommand<K, V>,
+    {
+        let mut notified = 0;
+        let mut full = Vec::new();
+
+        {
+            let sessions = self.sessions.read().await;
+            for &user_id in user_ids {
+                let Some(clients) = sessions.by_user.get(&user_id) else {
+                    continue;
+                };
+
+                for (&client_id, sender) in clients {
+                    match sender.try_send(command()) {
+                        Ok(()) => notified += 1,
+                        Err(TrySendError::Full(_)) => full.push((user_id, client_id)),
+                        Err(TrySendError::Closed(_)) => {}
+                    }
+                }
+            }
+        }
+
+        self.evict(&full).await;
+        notified
+    }
+}
+
+impl<K, V> Sessions<K, V> {
+    fn remove(&mut self, user_id: Uuid, client_id: ClientId) {
+        self.users.remove(&client_id);
+        if let Some(clients) = self.by_user.get_mut(&user_id) {
+            clients.remove(&client_id);
+            if clients.is_empty() {
+                self.by_user.remove(&user_id);
+            }
+        }
+    }
+}
+
+impl<K, V> Default for SessionRegistry<K, V>
+where
+    K: std::hash::Hash + Eq + Clone,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn delivers_to_every_session_of_a_user() {
+        let registry = SessionRegistry::<u32, u32>::new();
+        let user_id = Uuid::new_v4();
+        let mut first = registry.register(user_id, Uuid::new_v4()).await;
+        let mut second = registry.register(user_id, Uuid::new_v4()).await;
+
+        assert_eq!(registry.send_to_user(user_id, 7).await, 2);
+        assert!(matches!(first.recv().await, Some(SessionCommand::Deliver(7))));
+        assert!(matches!(second.recv().await, Some(SessionCommand::Deliver(7))));
+    }
+
+    #[tokio::test]
+    async fn evicts_sessions_that_fall_behind() {
+        let registry = SessionRegistry::<u32, u32>::new();
+        let (user_id, client_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut commands = registry.register(user_id, client_id).await;
+
+        for i in 0..SESSION_COMMAND_BUFFER as u32 {
+            assert!(registry.send_to_client(client_id, i).await);
+        }
+        assert!(!registry.send_to_client(client_id, 0).await);
+        assert!(registry.online_users(&[user_id]).await.is_empty());
+
+        let mut received = 0;
+        while commands.recv().await.is_some() {
+            received += 1;
+        }
+        assert_eq!(received, SESSION_COMMAND_BUFFER);
+    }
+}
@@ -0,0 +1,12 @@
+-- Guild invites
+CREATE TABLE guild_invites (
+    code VARCHAR(16) PRIMARY KEY,
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    inviter_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    max_uses INTEGER,
+    uses INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX idx_guild_invites_guild_id ON guild_invites(guild_id);
\ No newline at end of file
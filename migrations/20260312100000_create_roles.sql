@@ -0,0 +1,28 @@
+-- Guild roles
+CREATE TABLE roles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    guild_id UUID NOT NULL REFERENCES guilds(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    permissions BIGINT NOT NULL DEFAULT 0, -- bitflags, see blazing_models::Permissions
+    position INTEGER NOT NULL DEFAULT 0,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE, -- the implicit '@everyone' role
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX idx_roles_guild_id ON roles(guild_id);
+CREATE UNIQUE INDEX idx_roles_guild_default ON roles(guild_id) WHERE is_default;
+
+-- Roles assigned to guild members
+CREATE TABLE member_roles (
+    guild_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, role_id),
+    FOREIGN KEY (guild_id, user_id) REFERENCES guild_members(guild_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_member_roles_role_id ON member_roles(role_id);
+
+-- Give existing guilds an '@everyone' role with the default permissions
+INSERT INTO roles (guild_id, name, permissions, is_default)
+SELECT id, '@everyone', 6663, TRUE FROM guilds;
\ No newline at end of file
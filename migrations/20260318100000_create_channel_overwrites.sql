@@ -0,0 +1,18 @@
+-- Per-channel permission overwrites for roles and members
+CREATE TABLE channel_overwrites (
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    target_type VARCHAR(10) NOT NULL, -- 'role', 'member'
+    target_id UUID NOT NULL,
+    allow BIGINT NOT NULL DEFAULT 0,
+    deny BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (channel_id, target_id)
+);
+
+CREATE INDEX idx_channel_overwrites_target_id ON channel_overwrites(target_id);
+
+-- Announcement channels are read-only for '@everyone' (deny SEND_MESSAGES)
+INSERT INTO channel_overwrites (channel_id, target_type, target_id, allow, deny)
+SELECT c.id, 'role', r.id, 0, 2
+FROM channels c
+INNER JOIN roles r ON r.guild_id = c.guild_id AND r.is_default
+WHERE c.type = 'announcement';
\ No newline at end of file
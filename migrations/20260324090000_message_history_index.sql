@@ -0,0 +1,5 @@
+-- Keyset pagination of message history walks (channel_id, created_at, id)
+DROP INDEX IF EXISTS idx_messages_channel_id;
+DROP INDEX IF EXISTS idx_messages_created_at;
+
+CREATE INDEX idx_messages_channel_created ON messages(channel_id, created_at DESC, id DESC);
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Soft-deleted messages are kept but hidden from history
+ALTER TABLE messages ADD COLUMN deleted_at TIMESTAMPTZ;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Replies reference the message they answer
+ALTER TABLE messages ADD COLUMN reply_to UUID REFERENCES messages(id) ON DELETE SET NULL;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- Emoji reactions, one per (message, user, emoji)
+CREATE TABLE message_reactions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    emoji VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (message_id, user_id, emoji)
+);
+
+-- New ADD_REACTIONS permission (1 << 14) is part of the '@everyone' defaults
+UPDATE roles SET permissions = permissions | 16384 WHERE is_default;
\ No newline at end of file
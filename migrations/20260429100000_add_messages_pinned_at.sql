@@ -0,0 +1,4 @@
+-- Pinned messages, listed per channel by pin time
+ALTER TABLE messages ADD COLUMN pinned_at TIMESTAMPTZ;
+
+CREATE INDEX idx_messages_pinned ON messages(channel_id, pinned_at DESC) WHERE pinned_at IS NOT NULL;
\ No newline at end of file
@@ -0,0 +1,17 @@
+-- Mentions parsed from message content
+ALTER TABLE messages ADD COLUMN mention_everyone BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE message_mentions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (message_id, user_id)
+);
+
+CREATE TABLE message_role_mentions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    PRIMARY KEY (message_id, role_id)
+);
+
+CREATE INDEX idx_message_mentions_user_id ON message_mentions(user_id);
+CREATE INDEX idx_message_role_mentions_role_id ON message_role_mentions(role_id);
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Direct message channels live outside of guilds and have their own recipients
+ALTER TABLE channels ALTER COLUMN guild_id DROP NOT NULL;
+ALTER TABLE channels ADD CONSTRAINT channels_guild_kind
+    CHECK ((guild_id IS NULL) = (type IN ('dm', 'group_dm')));
+
+CREATE TABLE dm_recipients (
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    joined_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (channel_id, user_id)
+);
+
+CREATE INDEX idx_dm_recipients_user_id ON dm_recipients(user_id);
\ No newline at end of file
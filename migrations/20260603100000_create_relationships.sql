@@ -0,0 +1,12 @@
+-- Friends, pending friend requests and blocks. Each user keeps their own row for the
+-- other, so a friendship or request is two rows and a block is one.
+CREATE TABLE relationships (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    target_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    type VARCHAR(20) NOT NULL, -- 'friend', 'pending_incoming', 'pending_outgoing', 'blocked'
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (user_id, target_id),
+    CHECK (user_id <> target_id)
+);
+
+CREATE INDEX idx_relationships_target_id ON relationships(target_id);
\ No newline at end of file
@@ -0,0 +1,26 @@
+-- Threads are channels nested under a guild channel, whose permissions they inherit
+ALTER TABLE channels ADD COLUMN parent_id UUID REFERENCES channels(id) ON DELETE CASCADE;
+ALTER TABLE channels ADD CONSTRAINT channels_thread_parent
+    CHECK ((parent_id IS NULL) = (type <> 'thread'));
+
+CREATE INDEX idx_channels_parent_id ON channels(parent_id) WHERE parent_id IS NOT NULL;
+
+CREATE TABLE threads (
+    channel_id UUID PRIMARY KEY REFERENCES channels(id) ON DELETE CASCADE,
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    starter_message_id UUID UNIQUE REFERENCES messages(id) ON DELETE SET NULL,
+    auto_archive_duration INTEGER NOT NULL DEFAULT 1440,
+    archived_at TIMESTAMPTZ,
+    last_activity_at TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX idx_threads_last_activity_at ON threads(last_activity_at) WHERE archived_at IS NULL;
+
+CREATE TABLE thread_members (
+    thread_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    joined_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (thread_id, user_id)
+);
+
+CREATE INDEX idx_thread_members_user_id ON thread_members(user_id);
\ No newline at end of file
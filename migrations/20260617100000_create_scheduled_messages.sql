@@ -0,0 +1,17 @@
+-- Messages waiting to be posted. A delivered message takes the id of its scheduled
+-- message, so a delivery interrupted by a restart is never posted twice.
+CREATE TABLE scheduled_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    reply_to UUID,
+    send_at TIMESTAMPTZ NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    claimed_at TIMESTAMPTZ,
+    error TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX idx_scheduled_messages_due ON scheduled_messages(send_at) WHERE status <> 'failed';
+CREATE INDEX idx_scheduled_messages_author_id ON scheduled_messages(author_id);
\ No newline at end of file
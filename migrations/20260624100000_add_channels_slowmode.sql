@@ -0,0 +1,3 @@
+-- Minimum delay between two messages of the same user in a channel, 0 when disabled
+ALTER TABLE channels ADD COLUMN slowmode_seconds INTEGER NOT NULL DEFAULT 0
+    CHECK (slowmode_seconds BETWEEN 0 AND 21600);
\ No newline at end of file